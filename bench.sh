@@ -0,0 +1,24 @@
+# generates a large unit and times the matcher over it, the generated file and
+# timings end up in bench_output.txt
+LINES=${1:-2000}
+UNIT=target/bench.pi
+
+mkdir -p target
+cargo build --release -q || exit 1
+
+: > $UNIT
+for i in $(seq 1 $LINES); do
+    case $((i % 4)) in
+        0) echo "mut v$i = a + b - c + sum(f(1, g(2, h(3, k(4, m(5, n(6, 7)))))), 8) - 9" ;;
+        1) echo "v$i = sum(1, 2) + v$((i - 1)) - max(a, min(b, c))" ;;
+        2) echo "let w$i: i32 = 1 + 2 + 3 + 4 + 5 + 6 + 7 + 8 + 9" ;;
+        3) echo "print(\"line $i\", v$((i - 2)))" ;;
+    esac >> $UNIT
+done
+
+{
+    echo "== $LINES lines =="
+    time ./target/release/synth check -t tests/test.t $UNIT
+} > bench_output.txt 2>&1
+
+cat bench_output.txt
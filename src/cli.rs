@@ -0,0 +1,140 @@
+pub const USAGE: &str = "usage: synth <command> -t <template> [options] <units>
+
+commands:
+    build    generate target code into the output directory, or stdout
+    check    report diagnostics without generating anything
+    tokens   print the tokens of each unit, or of the template if none given
+    ast      print the matched tree of each unit
+
+options:
+    -t, --template <file>   template describing the language
+    -o, --output <dir>      directory generated files are written to
+    -q, --quiet             only report errors
+    -v, --verbose           report each unit as it is processed
+        --color=<when>      auto, always or never
+    -h, --help              print this message";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Command {
+    Build,
+    Check,
+    Tokens,
+    Ast,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub command:  Command,
+    pub template: String,
+    pub output:   Option<String>,
+    pub units:    Vec<String>,
+
+    pub quiet:    bool,
+    pub verbose:  bool,
+    pub color:    Color,
+}
+
+impl Command {
+    pub fn from_str (s: &str) -> Option<Self> {
+        match s {
+            "build"  => Some(Command::Build),
+            "check"  => Some(Command::Check),
+            "tokens" => Some(Command::Tokens),
+            "ast"    => Some(Command::Ast),
+
+            _ => None,
+        }
+    }
+}
+
+impl Color {
+    pub fn from_str (s: &str) -> Option<Self> {
+        match s {
+            "auto"   => Some(Color::Auto),
+            "always" => Some(Color::Always),
+            "never"  => Some(Color::Never),
+
+            _ => None,
+        }
+    }
+}
+
+/// parses the arguments following the program name, `Ok(None)` asks for help
+pub fn parse<I> (args: I) -> Result<Option<Options>, String>
+    where I: IntoIterator<Item = String> {
+
+    let mut args = args.into_iter();
+
+    let command = match args.next() {
+        Some(ref a) if a == "-h" || a == "--help" => return Ok(None),
+        Some(a) => match Command::from_str(&a) {
+            Some(c) => c,
+            None    => return Err(format!("unknown command '{}'", a)),
+        },
+        None => return Err("no command given".to_string()),
+    };
+
+    let mut template = None;
+    let mut output   = None;
+    let mut units    = Vec::new();
+
+    let mut quiet   = false;
+    let mut verbose = false;
+    let mut color   = Color::Auto;
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "-h" | "--help"     => return Ok(None),
+            "-q" | "--quiet"    => quiet = true,
+            "-v" | "--verbose"  => verbose = true,
+            "-t" | "--template" => template = Some(value(&arg, args.next())?),
+            "-o" | "--output"   => output = Some(value(&arg, args.next())?),
+            "--color"           => color = color_value(&value(&arg, args.next())?)?,
+
+            _ if arg.starts_with("--color=") => color = color_value(&arg["--color=".len()..])?,
+            _ if arg.starts_with('-') && arg.len() > 1 => return Err(format!("unknown option '{}'", arg)),
+
+            _ => units.push(arg),
+        }
+    }
+
+    if quiet && verbose {
+        return Err("--quiet and --verbose can't be used together".to_string())
+    }
+
+    let template = match template {
+        Some(t) => t,
+        None    => return Err("no template given, specify one with -t".to_string()),
+    };
+
+    if units.is_empty() && (command == Command::Build || command == Command::Ast) {
+        return Err("no units given".to_string())
+    }
+
+    if output.is_some() && command != Command::Build {
+        return Err("-o is only valid for build".to_string())
+    }
+
+    Ok(Some(Options { command, template, output, units, quiet, verbose, color }))
+}
+
+fn value (option: &str, value: Option<String>) -> Result<String, String> {
+    match value {
+        Some(v) => Ok(v),
+        None    => Err(format!("{} expects a value", option)),
+    }
+}
+
+fn color_value (value: &str) -> Result<Color, String> {
+    match Color::from_str(value) {
+        Some(c) => Ok(c),
+        None    => Err(format!("invalid color '{}', expected auto, always or never", value)),
+    }
+}
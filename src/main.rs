@@ -1,89 +1,199 @@
 extern crate colored;
 
+use std::fs;
 use std::fs::File;
 use std::path::Path;
 use std::io::BufReader;
 use std::io::prelude::*;
+use std::io::IsTerminal;
 use std::sync::Arc;
+use std::process;
+
+use colored::*;
 
 mod synth;
+mod cli;
 
 use synth::*;
 
 use tokenizer::tokenizer::*;
 use parser::*;
+use error::*;
+use cli::{Command, Color, Options};
 
 use std::env;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    // colour by default only when a person is reading, even argument errors
+    colored::control::set_override(std::io::stderr().is_terminal());
+
+    let options = match cli::parse(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            return
+        },
+        Err(message) => {
+            eprintln!("{}: {}\n\n{}", "error".red().bold(), message, cli::USAGE);
+            process::exit(2)
+        },
+    };
+
+    match options.color {
+        Color::Always => colored::control::set_override(true),
+        Color::Never  => colored::control::set_override(false),
+        Color::Auto   => (),
+    }
+
+    if !run(&options) {
+        process::exit(1)
+    }
+}
+
+/// runs a command over every unit, returns false if anything failed
+fn run (options: &Options) -> bool {
+    let mut success = true;
+
+    // template -------------------------------------
+    let t_lines = match read_lines(&options.template) {
+        Ok(lines) => lines,
+        Err(message) => {
+            eprintln!("{}: {}", "error".red().bold(), message);
+            return false
+        },
+    };
+
+    let mut t_src = Source::new(&options.template, Some("!/def/"), &t_lines);
+    if let Err(ref e) = t_src.tokenize() {
+        report(e, &t_lines, options);
+        return false
+    }
+
+    if options.command == Command::Tokens && options.units.is_empty() {
+        t_src.dump();
+        return true
+    }
+
+    let mut t = template::Template::new(&t_src);
+    if let Err(ref e) = t.parse() {
+        report(e, &t_lines, options);
+        return false
+    }
+
+    let t_arc = Arc::new(t);
+
+    let extension = t_arc.source.get_directive("extension").unwrap_or("c").trim();
+
+    if let Some(ref dir) = options.output {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("{}: can't create output directory {}: {}", "error".red().bold(), dir, e);
+            return false
+        }
+    }
+
+
+    // units ----------------------------------------
+    for unit in options.units.iter() {
+        if options.verbose {
+            eprintln!("{} {}", "processing".green().bold(), unit);
+        }
 
-    if args.len() == 2 {
-        if !Path::new(&args[1]).exists() {
-            panic!("no such file: {}", &args[1]);
+        let u_lines = match read_lines(unit) {
+            Ok(lines) => lines,
+            Err(message) => {
+                eprintln!("{}: {}", "error".red().bold(), message);
+                success = false;
+                continue
+            },
+        };
+
+        let mut u_src = Source::new(unit, None, &u_lines);
+        u_src.directives = t_arc.source.directives.clone();
+        if let Err(ref e) = u_src.tokenize() {
+            report(e, &u_lines, options);
+            success = false;
+            continue
         }
-        panic!("specify source files after template");
 
-    } else if args.len() > 2 {
-        println!("== started ==");
+        if options.command == Command::Tokens {
+            u_src.dump();
+            continue
+        }
 
-        // template -------------------------------------
-        println!("\n== template ==");
-        let t_f: File = match File::open(&args[1]) {
-            Ok(v) => v,
-            Err(_) => panic!("no such file: {}", &args[1]),
+        let mut u = unit::Unit::new(&u_src, t_arc.clone());
+        let ast = match u.parse() {
+            Ok(ast) => ast,
+            Err(ref e) => {
+                report(e, &u_lines, options);
+                success = false;
+                continue
+            },
         };
 
-        let t_raw  = BufReader::new(&t_f);
-        let t_lines = t_raw.lines().map(|x| x.unwrap()).collect();
+        ast.analyse();
+
+        if options.command == Command::Ast {
+            ast.dump();
+            continue
+        }
 
-        let mut t_src = Source::new(&args[1], Some("!/def/"), &t_lines);
-        match t_src.tokenize() {
+        let output = match ast.compile() {
+            Ok(output) => output,
             Err(ref e) => {
-                e.dump(&t_lines);
-                return
+                report(e, &u_lines, options);
+                success = false;
+                continue
             },
+        };
+
+        report(&output, &u_lines, options);
 
-            Ok(())     => (),
+        if options.command == Command::Check {
+            continue
         }
 
-        let mut t = template::Template::new(&t_src);
-        t.parse();
+        if let Some(ref dir) = options.output {
+            let stem = Path::new(unit).file_stem().unwrap().to_string_lossy();
+            let target = Path::new(dir).join(format!("{}.{}", stem, extension));
 
-        let t_arc = Arc::new(t);
+            if let Err(e) = fs::write(&target, format!("{}\n", output.value)) {
+                eprintln!("{}: can't write {}: {}", "error".red().bold(), target.display(), e);
+                success = false;
 
+            } else if options.verbose {
+                eprintln!("{} {}", "wrote".green().bold(), target.display());
+            }
 
-        // units ----------------------------------------
-        let mut unit_iter = args.iter();
-        unit_iter.nth(1);
+        } else {
+            println!("{}", output.value);
+        }
+    }
 
-        for unit in unit_iter {
-            println!("\n== unit {} ==", &unit);
+    success
+}
 
-            let u_f: File = match File::open(&unit) {
-                Ok(v) => v,
-                Err(_) => panic!("no such file: {}", &unit),
-            };
+fn read_lines (path: &str) -> Result<Vec<String>, String> {
+    let file = match File::open(path) {
+        Ok(f)  => f,
+        Err(e) => return Err(format!("can't open {}: {}", path, e)),
+    };
 
-            let u_raw  = BufReader::new(&u_f);
-            let u_lines = u_raw.lines().map(|x| x.unwrap()).collect();
+    match BufReader::new(file).lines().collect() {
+        Ok(lines) => Ok(lines),
+        Err(e)    => Err(format!("can't read {}: {}", path, e)),
+    }
+}
 
-            let mut u_src = Source::new(unit, None, &u_lines);
-            u_src.directives = t_arc.source.directives.clone();
-            match u_src.tokenize() {
-                Err(ref e) => e.dump(&u_lines),
-                Ok(())     => (),
-            }
+/// dumps diagnostics, leaving out warnings and notes when quiet
+fn report<T> (outcome: &Outcome<T>, lines: &[String], options: &Options) {
+    if options.quiet {
+        let errors = outcome.response.as_ref().map(
+            |r| r.iter().filter(|r| r.is_error()).cloned().collect()
+        );
 
-            let mut u = unit::Unit::new(&u_src, t_arc.clone());
-            let ast = u.parse();
-            ast.analyse();
-            ast.compile();
-        }
+        Outcome::new((), errors).dump(lines)
 
     } else {
-        println!("source file not supplied: synth <file>")
+        outcome.dump(lines)
     }
-
-    println!("\n== finished ==");
 }
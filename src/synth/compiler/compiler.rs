@@ -1,28 +1,189 @@
 use tokenizer::tokenizer::Source;
+use tokenizer::token::Token;
+use parser::template::Template;
+use std::rc::Rc;
 use parser::unit::Node;
+use parser::rule::Segment;
+use error::*;
 
 
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct AST<'a> {
-    source: &'a Source<'a>,
-    ast: Vec<Node<'a>>,
+    pub source: &'a Source<'a>,
+    pub template: &'a Template<'a, 'a>,
+    ast: Vec<Rc<Node<'a>>>,
 }
 
 
 impl<'a> AST<'a> {
-    pub fn new (source: &'a Source<'a>, ast: Vec<Node<'a>>) -> AST<'a> {
-        AST { source, ast }
+    pub fn new (source: &'a Source<'a>, template: &'a Template<'a, 'a>, ast: Vec<Rc<Node<'a>>>) -> AST<'a> {
+        AST { source, template, ast }
     }
 
     pub fn analyse (&self) {}
 
 
-    pub fn compile (&self) {
+    /// generates the target code, warnings and notes are returned alongside it
+    pub fn compile (&self) -> CompileResult<'a, Outcome<'a, String>, ()> {
+        let mut response = Vec::new();
+        let mut output = Vec::new();
+
         for node in self.ast.iter() {
-            dump_node(&self.source, &node, 1);
-            println!("");
+            let text = self.render(node);
+
+            if !text.is_empty() {
+                output.push(text);
+            }
+
+            self.diagnose(node, &mut response);
         }
+
+        // comments after the last statement lead no node, so they close the output
+        if self.template.keeps_comments() {
+            let end = self.ast.last().map_or(0, |node| node.span.1);
+
+            output.extend(self.source.comments.iter()
+                .filter(|comment| !comment.trailing && comment.token >= end)
+                .map(|comment| self.source.comment_text(comment).into_owned()));
+        }
+
+        if response.iter().any(Response::is_error) {
+            Err(Outcome::new((), Some(response)))
+        } else if !response.is_empty() {
+            Ok(Outcome::new(output.join("\n"), Some(response)))
+        } else {
+            Ok(Outcome::new(output.join("\n"), None))
+        }
+    }
+
+    pub fn dump (&self) {
+        for node in self.ast.iter() {
+            dump_node(self.source, node, 1);
+            println!();
+        }
+    }
+
+    /// renders a node through the `[output]` segment of its variant,
+    /// falling back to the one declared on its rule
+    pub fn render (&self, node: &Node<'a>) -> String {
+        match self.output(node) {
+            Some(segment) => segment.evaluate(self, node),
+            None          => String::new(),
+        }
+    }
+
+    /// reports the diagnostics of a node and of every node under it, once each
+    /// whether or not output writes them
+    fn diagnose (&self, node: &Node<'a>, response: &mut Vec<Response<'a>>) {
+        if let Some(segment) = self.output(node) {
+            segment.diagnose(self, node, response);
+        }
+
+        for child in node.children.iter() {
+            self.diagnose(&child.node, response);
+        }
+    }
+
+    /// resolves a `{name}` placeholder against the aliases of a node,
+    /// child nodes can be referred to by alias or by rule name,
+    /// the matches of a repeated alias are joined by the separator
+    pub fn placeholder (&self, node: &Node<'a>, name: &str, separator: &str) -> Option<String> {
+        let tokens = self.source.tokens.as_ref().unwrap();
+
+        match name {
+            "@comments" => return Some(self.comments(node, false)),
+            "@trailing" => return Some(self.comments(node, true)),
+            _ => (),
+        }
+
+        let bound = node.tokens.iter().any(|alias| alias.name.lexeme == Some(name))
+            || node.children.iter().any(|child| child.alias.lexeme == Some(name));
+
+        let alias = if bound {
+            name
+        } else {
+            match node.children.iter().find(|child| child.node.variant.rule == name) {
+                Some(child) => child.alias.lexeme.unwrap(),
+
+                // an element that matched zero times renders as nothing
+                None if node.variant.binds(name) => return Some(String::new()),
+                None => return None,
+            }
+        };
+
+        let mut values: Vec<String> = node.tokens.iter()
+            .filter(|token| token.name.lexeme == Some(alias))
+            .map(|token| self.source.text(&tokens[token.token]).into_owned())
+            .collect();
+
+        for child in node.children.iter() {
+            if child.alias.lexeme == Some(alias) {
+                values.push(self.render(&child.node));
+            }
+        }
+
+        Some(values.join(separator))
+    }
+
+    /// comments leading a node, each on a line of its own ahead of its code,
+    /// or trailing the tokens of a node, each after a space
+    fn comments (&self, node: &Node<'a>, trailing: bool) -> String {
+        self.source.comments.iter()
+            .filter(|comment| comment.trailing == trailing)
+            .filter(|comment| match trailing {
+                true  => comment.token >= node.span.0 && comment.token < node.span.1,
+                false => comment.token == node.span.0,
+            })
+            .map(|comment| match trailing {
+                true  => format!(" {}", self.source.comment_text(comment)),
+                false => format!("{}\n", self.source.comment_text(comment)),
+            })
+            .collect()
+    }
+
+    /// whether an alias of a node, or a child referred to by rule name, matched anything
+    pub fn is_bound (&self, node: &Node<'a>, name: &str) -> bool {
+        node.tokens.iter().any(|alias| alias.name.lexeme == Some(name))
+        || node.children.iter().any(|child| child.alias.lexeme == Some(name) || child.node.variant.rule == name)
+    }
+
+    /// finds the unit token bound to an alias of a node,
+    /// child nodes are located by the first token they matched
+    pub fn locate (&self, node: &Node<'a>, name: &str) -> Option<&'a Token<'a>> {
+        let tokens = self.source.tokens.as_ref().unwrap();
+
+        for alias in node.tokens.iter() {
+            if alias.name.lexeme == Some(name) {
+                return Some(&tokens[alias.token])
+            }
+        }
+
+        for child in node.children.iter() {
+            if child.alias.lexeme == Some(name) || child.node.variant.rule == name {
+                return self.first(&child.node)
+            }
+        }
+
+        None
+    }
+
+    /// position of the token bound to an alias, falling back to the start of the node
+    pub fn position (&self, node: &Node<'a>, name: &str) -> Position {
+        match self.locate(node, name).or_else(|| self.first(node)) {
+            Some(token) => token.position(),
+            None        => Position::new((self.source.lines.len(), 0), 0),
+        }
+    }
+
+    fn first (&self, node: &Node<'a>) -> Option<&'a Token<'a>> {
+        self.source.tokens.as_ref().unwrap().get(node.span.0)
+    }
+
+    fn output (&self, node: &Node<'a>) -> Option<&'a Segment<'a, 'a>> {
+        node.variant.segment("output")
+            .or_else(|| node.variant.rule(self.template).segment("output"))
     }
 }
 
@@ -32,7 +193,7 @@ fn dump_node(source: &Source, node: &Node, dent: usize) {
 
     print!("{}", node.variant.name.lexeme.unwrap());
 
-    if node.tokens.len() > 0 {
+    if !node.tokens.is_empty() {
         print!(" ( ");
     }
 
@@ -43,13 +204,13 @@ fn dump_node(source: &Source, node: &Node, dent: usize) {
         );
     }
 
-    if node.tokens.len() > 0 {
+    if !node.tokens.is_empty() {
         println!(")");
     } else {
-        println!("");
+        println!();
     }
 
     for child in node.children.iter() {
-        dump_node(source, child, dent+1);
+        dump_node(source, &child.node, dent+1);
     }
 }
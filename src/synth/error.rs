@@ -1,5 +1,5 @@
 use colored::*;
-use std::rc::Rc;
+use std::borrow::Cow;
 
 #[derive(Debug, Clone)]
 pub struct Position {
@@ -18,9 +18,15 @@ impl Position {
 
 #[derive(Debug, Clone)]
 pub enum Response<'r> {
-    Note(&'r str,    Position),
-    Warning(&'r str, Position),
-    Error(&'r str,   Position),
+    Note(Cow<'r, str>,    Position),
+    Warning(Cow<'r, str>, Position),
+    Error(Cow<'r, str>,   Position),
+}
+
+impl<'r> Response<'r> {
+    pub fn is_error(&self) -> bool {
+        matches!(*self, Response::Error(..))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -45,55 +51,59 @@ impl<'o, T> Outcome<'o, T> {
         }
     }
 
-    pub fn dump(&self, lines: &Vec<String>) {
+    pub fn dump(&self, lines: &[String]) {
         if self.response.is_some() {
             for value in self.response.clone().unwrap().iter() {
                 match *value {
                     Response::Error(ref v, ref pos) => {
-                        println!("{}: {}", "error".red().bold(), v.white().bold());
+                        eprintln!("{}: {}", "error".red().bold(), v.as_ref().white().bold());
 
                         let line = format!("{} |", pos.position.0).blue().bold();
-                        println!("{}{}", line, lines.get(pos.position.0 - 1).unwrap());
+                        eprintln!("{}{}", line, lines.get(pos.position.0 - 1).unwrap());
 
                         for _ in 0 .. line.len() + pos.position.1 {
-                            print!(" ")
+                            eprint!(" ")
                         }
 
                         for _ in 0 .. pos.span {
-                            print!("{}", "^".red().bold())
+                            eprint!("{}", "^".red().bold())
                         }
 
-                        println!()
+                        eprintln!()
                     },
                     
                     Response::Warning(ref v, ref pos) => {
-                        println!("{}: {}", "warning".yellow().bold(), v.white().bold());
+                        eprintln!("{}: {}", "warning".yellow().bold(), v.as_ref().white().bold());
 
                         let line = format!("{} |", pos.position.0).blue().bold();
-                        println!("{}{}", line, lines.get(pos.position.0 - 1).unwrap());
+                        eprintln!("{}{}", line, lines.get(pos.position.0 - 1).unwrap());
 
                         for _ in 0 .. line.len() + pos.position.1 {
-                            print!(" ")
+                            eprint!(" ")
                         }
 
                         for _ in 0 .. pos.span {
-                            print!("{}", "^".yellow().bold())
+                            eprint!("{}", "^".yellow().bold())
                         }
+
+                        eprintln!()
                     },
                     
                     Response::Note(ref v, ref pos) => {
-                        println!("{}: {}", "note".white().bold(), v.white().bold());
+                        eprintln!("{}: {}", "note".white().bold(), v.as_ref().white().bold());
 
                         let line = format!("{} |", pos.position.0).blue().bold();
-                        println!("{}{}", line, lines.get(pos.position.0 - 1).unwrap());
+                        eprintln!("{}{}", line, lines.get(pos.position.0 - 1).unwrap());
 
                         for _ in 0 .. line.len() + pos.position.1 {
-                            print!(" ")
+                            eprint!(" ")
                         }
 
                         for _ in 0 .. pos.span {
-                            print!("{}", "^".white().bold())
+                            eprint!("{}", "^".white().bold())
                         }
+
+                        eprintln!()
                     },
                 }
             }
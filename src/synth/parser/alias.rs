@@ -4,11 +4,47 @@ use tokenizer::token::Token;
 pub struct Alias<'t, 's: 't> {
     pub name: &'t Token<'s>,
     pub token: usize,
+    pub repeat: Repeat<'t, 's>,
+}
+
+/// how many times an aliased pattern element matches,
+/// `x:e?`, `x:e*` and `x:e+`, optionally separated as in `x:e % ,`
+#[derive(Debug, Clone, Copy)]
+pub struct Repeat<'t, 's: 't> {
+    pub min: usize,
+    pub max: Option<usize>,
+    pub separator: Option<&'t Token<'s>>,
 }
 
 
 impl<'t, 's: 't> Alias<'t, 's> {
     pub fn new(name: &'t Token<'s>, token: usize) -> Self {
-        Self { name, token }
+        Self { name, token, repeat: Repeat::once() }
+    }
+
+    pub fn repeated(name: &'t Token<'s>, token: usize, repeat: Repeat<'t, 's>) -> Self {
+        Self { name, token, repeat }
+    }
+}
+
+impl<'t, 's: 't> Repeat<'t, 's> {
+    pub fn once () -> Self {
+        Self { min: 1, max: Some(1), separator: None }
+    }
+
+    /// reads a quantifier symbol, `None` if it isn't one
+    pub fn from_str (s: &str) -> Option<Self> {
+        match s {
+            "?" => Some(Self { min: 0, max: Some(1), separator: None }),
+            "*" => Some(Self { min: 0, max: None, separator: None }),
+            "+" => Some(Self { min: 1, max: None, separator: None }),
+
+            _ => None,
+        }
+    }
+
+    /// whether the element can match more than once
+    pub fn is_list (&self) -> bool {
+        self.max != Some(1)
     }
 }
@@ -1,7 +1,14 @@
 use tokenizer::token::Token;
+use tokenizer::token::Type::{Word, Symbol, EOL, Indent, Dedent};
+use tokenizer::token::Type::String as Str;
+use tokenizer::token::PartialToken::{Type, Pair};
 use template::Template;
-use alias::Alias;
+use alias::{Alias, Repeat};
 use unit::Node;
+use tokenizer::tokenizer::Source;
+use compiler::compiler::AST;
+use extras::string::StringExtras;
+use error::*;
 
 #[derive(Debug)]
 pub struct Rule<'t, 's: 't> {
@@ -9,6 +16,7 @@ pub struct Rule<'t, 's: 't> {
     pub is_matching: bool,
     pub variants: Vec<Variant<'t, 's>>,
     pub segments: Vec<Segment<'t, 's>>,
+    pub operators: Vec<Operator<'s>>,
 }
 
 #[derive(Debug)]
@@ -19,15 +27,38 @@ pub struct Variant<'t, 's: 't> {
     pub tokens: Vec<&'t Token<'s>>,
     pub segments: Vec<Segment<'t, 's>>,
     pub aliases: Vec<Alias<'t, 's>>,
+    pub groups: Vec<Group<'t, 's>>,
 }
 
+/// a parenthesized part of a pattern, `start` and `end` index its parentheses
+/// and each branch is the range of elements between its `|`s
 #[derive(Debug)]
-pub struct Segment<'t, 's: 't> {
-    pub name: &'t Token<'s>,
+pub struct Group<'t, 's: 't> {
+    pub start: usize,
+    pub end: usize,
+    pub branches: Vec<(usize, usize)>,
+    pub repeat: Repeat<'t, 's>,
+}
 
-    pub rule: &'t str,
-    pub variant: Option<&'t str>,
+/// an entry of the operator table of a rule,
+/// levels count up from the loosest binding operators
+#[derive(Debug)]
+pub struct Operator<'s> {
+    pub symbol: &'s str,
+    pub level: usize,
+    pub assoc: Assoc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+    Nonassoc,
+}
 
+#[derive(Debug)]
+pub struct Segment<'t, 's: 't> {
+    pub name: &'t Token<'s>,
     pub tokens: Vec<&'t Token<'s>>,
 }
 
@@ -36,29 +67,30 @@ impl<'t, 's: 't> Rule<'t, 's> {
         name: &'t Token<'s>,
         is_matching: bool,
         variants: Vec<Variant<'t, 's>>,
-        segments: Vec<Segment<'t, 's>>
+        segments: Vec<Segment<'t, 's>>,
+        operators: Vec<Operator<'s>>
     ) -> Self {
-        Self { name, is_matching, variants, segments }
+        Self { name, is_matching, variants, segments, operators }
     }
 
-    pub fn variant (&self, name: &str) -> Option<&Variant<'t, 's>> {
-        for var in self.variants.iter() {
+    pub fn segment (&self, name: &str) -> Option<&Segment<'t, 's>> {
+        for var in self.segments.iter() {
             if var.name.lexeme.unwrap() == name {
-                return Some(&var)
+                return Some(var)
             }
         }
 
         None
     }
 
-    pub fn segment (&self, name: &str) -> Option<&Segment<'t, 's>> {
-        for var in self.segments.iter() {
-            if var.name.lexeme.unwrap() == name {
-                return Some(&var)
-            }
-        }
+    pub fn operator (&self, symbol: &str) -> Option<&Operator<'s>> {
+        self.operators.iter().find(|op| op.symbol == symbol)
+    }
 
-        None
+    /// whether a variant combines operands through the operator table,
+    /// rather than being matched on its own
+    pub fn is_binary (&self, variant: &Variant<'t, 's>) -> bool {
+        !self.operators.is_empty() && variant.is_binary()
     }
 }
 
@@ -68,49 +100,365 @@ impl<'t, 's: 't> Variant<'t, 's> {
         rule: &'t str,
         tokens: Vec<&'t Token<'s>>,
         segments: Vec<Segment<'t, 's>>,
-        aliases: Vec<Alias<'t, 's>>
+        aliases: Vec<Alias<'t, 's>>,
+        groups: Vec<Group<'t, 's>>
     ) -> Self {
-        Self { name, rule, tokens, segments, aliases }
+        Self { name, rule, tokens, segments, aliases, groups }
     }
 
     pub fn segment (&self, name: &str) -> Option<&Segment<'t, 's>> {
         for var in self.segments.iter() {
             if var.name.lexeme.unwrap() == name {
-                return Some(&var)
+                return Some(var)
             }
         }
 
         None
     }
 
-    pub fn rule (&self, template: &'t Template) -> &Rule {
-        template.find_rule(&self.rule).unwrap()
+    pub fn rule<'a> (&self, template: &'a Template<'t, 's>) -> &'a Rule<'t, 's> {
+        template.find_rule(self.rule).unwrap()
+    }
+
+    /// whether the pattern is `l:rule o:op r:rule`, an operand of its own rule
+    /// on both sides of an operator rule
+    pub fn is_binary (&self) -> bool {
+        self.tokens.len() == 3 && self.groups.is_empty()
+        && (0..3).all(|index| self.alias(index).is_some_and(|alias| !alias.repeat.is_list() && alias.repeat.min == 1))
+        && self.tokens[0].lexeme == Some(self.rule) && self.tokens[2].lexeme == Some(self.rule)
+        && !self.tokens[1].lexeme.unwrap().is_uppercase()
+    }
+
+    /// the alias bound to the pattern element at an index, if any
+    pub fn alias (&self, index: usize) -> Option<&Alias<'t, 's>> {
+        self.aliases.iter().find(|alias| alias.token == index)
+    }
+
+    /// the group opened by the pattern element at an index, if any
+    pub fn group (&self, index: usize) -> Option<&Group<'t, 's>> {
+        self.groups.iter().find(|group| group.start == index)
+    }
+
+    /// whether the pattern elements at two indexes are in different branches
+    /// of the same group, so no match can include both
+    pub fn exclusive (&self, a: usize, b: usize) -> bool {
+        let branch = |group: &Group, index| group.branches.iter().position(|&(start, end)| start <= index && index < end);
+
+        self.groups.iter().any(|group| match (branch(group, a), branch(group, b)) {
+            (Some(x), Some(y)) => x != y,
+            _                  => false,
+        })
+    }
+
+    /// whether a name refers to an alias of this variant,
+    /// or to the rule of one of its aliased children
+    pub fn binds (&self, name: &str) -> bool {
+        self.aliases.iter().any(|alias| alias.name.lexeme == Some(name)) || self.targets(name) > 0
+    }
+
+    /// how many aliases of this variant match the rule of the given name
+    pub fn targets (&self, rule: &str) -> usize {
+        self.aliases.iter().filter(|alias| {
+            let target = self.tokens[alias.token];
+            target.token_type == Word && !target.lexeme.unwrap().is_uppercase() && target.lexeme == Some(rule)
+        }).count()
+    }
+}
+
+impl Assoc {
+    pub fn from_str (s: &str) -> Option<Self> {
+        match s {
+            "left"     => Some(Assoc::Left),
+            "right"    => Some(Assoc::Right),
+            "nonassoc" => Some(Assoc::Nonassoc),
+
+            _ => None,
+        }
     }
 }
 
 impl<'t, 's: 't> Segment<'t, 's> {
-    pub fn new (
-        name: &'t Token<'s>,
-        rule: &'t str,
-        variant: Option<&'t str>,
-        tokens: Vec<&'t Token<'s>>
-    ) -> Self {
-        Self { name, rule, variant, tokens }
+    pub fn new (name: &'t Token<'s>, tokens: Vec<&'t Token<'s>>) -> Self {
+        Self { name, tokens }
     }
 
-    pub fn rule (&self, template: &'t Template) -> &Rule {
-        template.find_rule(&self.rule).unwrap()
+    /// returns the text the `write:` lines of this segment produce for a matched node
+    pub fn evaluate (&self, ast: &AST<'s>, node: &Node<'s>) -> String {
+        let mut output = Vec::new();
+
+        self.actions(ast, node, |name, args| if name == "write" && !args.is_empty() {
+            let text = ast.template.source.span(args[0], args[args.len() - 1]);
+            output.push(substitute(text, |name, separator| ast.placeholder(node, name, separator)));
+        });
+
+        output.join("\n")
+    }
+
+    /// adds what the `error:`, `warning:` and `note:` lines of this segment report about a matched node
+    pub fn diagnose (&self, ast: &AST<'s>, node: &Node<'s>, response: &mut Vec<Response<'s>>) {
+        self.actions(ast, node, |name, args| if let Some((alias, message)) = diagnostic(args) {
+            let message = message.to_string().into();
+
+            match name {
+                "error"   => response.push(Response::Error(message, ast.position(node, alias))),
+                "warning" => response.push(Response::Warning(message, ast.position(node, alias))),
+                "note"    => response.push(Response::Note(message, ast.position(node, alias))),
+                _ => (),
+            }
+        });
     }
 
-    pub fn variant (&self, template: &'t Template) -> Option<&Variant> {
-        if self.variant != None {
-            Some(self.rule(&template).variant(self.variant.unwrap()).unwrap())
-        } else {
-            None
+    /// calls `act` with the name and arguments of each action line whose guards hold for a node
+    fn actions<F> (&self, ast: &AST<'s>, node: &Node<'s>, mut act: F)
+        where F: FnMut(&str, &[&'t Token<'s>]) {
+
+        for line in self.lines() {
+            let (name, guards, args) = match action(&line) {
+                Some(action) => action,
+                None         => continue,
+            };
+
+            if guards.iter().all(|&(bound, alias)| ast.is_bound(node, alias.lexeme.unwrap()) == bound) {
+                act(name, args);
+            }
         }
     }
 
-    pub fn evaluate (&self, node: &Node) {
-        println!("evaluating {}", self.name.lexeme.unwrap());
+    /// reports action lines that can't be read, name no known action,
+    /// or give a diagnostic without an alias and a message
+    pub fn validate (&self, response: &mut Vec<Response<'s>>) {
+        for line in self.lines() {
+            let (name, _, args) = match action(&line) {
+                Some(action) => action,
+                None => {
+                    response.push(Response::Error("malformed action, expected `name: arguments`".into(), line[0].position()));
+                    continue
+                },
+            };
+
+            match name {
+                "write" => (),
+
+                "error" | "warning" | "note" if diagnostic(args).is_none() => response.push(Response::Error(
+                    "expected an alias and a message, as in `name, \"message\"`".into(), line[0].position()
+                )),
+
+                "error" | "warning" | "note" => (),
+
+                _ => response.push(Response::Error(format!("unknown action {}", name).into(), line[0].position())),
+            }
+        }
     }
+
+    /// names the actions of this segment refer to, placeholders in `write:` lines
+    /// and the aliases diagnostics are positioned at
+    pub fn references (&self, source: &Source<'s>) -> Vec<(&'s str, Position)> {
+        let mut references = Vec::new();
+
+        for line in self.lines() {
+            let (name, guards, args) = match action(&line) {
+                Some(action) => action,
+                None         => continue,
+            };
+
+            for &(_, alias) in guards.iter() {
+                references.push((alias.lexeme.unwrap(), alias.position()));
+            }
+
+            match name {
+                "write" if !args.is_empty() => {
+                    let (start, end) = source.bounds(args[0], args[args.len() - 1]);
+                    let text = &source.lines[args[0].line - 1][start..end];
+
+                    for (from, to, name, _) in placeholders(text) {
+                        references.push((name, Position::new((args[0].line, start + from), to - from)));
+                    }
+                },
+
+                "error" | "warning" | "note" if diagnostic(args).is_some() => {
+                    references.push((args[0].lexeme.unwrap(), args[0].position()));
+                },
+
+                _ => (),
+            }
+        }
+
+        references
+    }
+
+    /// splits the segment body into lines of tokens, dropping layout tokens
+    pub fn lines (&self) -> Vec<Vec<&'t Token<'s>>> {
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+
+        for &token in self.tokens.iter() {
+            if token == &Type(EOL) {
+                if !line.is_empty() {
+                    lines.push(line);
+                    line = Vec::new();
+                }
+
+            } else if token != &Type(Indent) && token != &Type(Dedent) {
+                line.push(token);
+            }
+        }
+
+        if !line.is_empty() {
+            lines.push(line);
+        }
+
+        lines
+    }
+}
+
+// name, guards and arguments of an action line
+type Action<'a, 't, 's> = (&'s str, Vec<(bool, &'t Token<'s>)>, &'a [&'t Token<'s>]);
+
+/// splits an action line into its name, the `if alias` and `unless alias`
+/// conditions guarding it and the arguments following its colon
+fn action<'a, 't, 's> (line: &'a [&'t Token<'s>]) -> Option<Action<'a, 't, 's>> {
+    if line.is_empty() || *line[0] != Type(Word) {
+        return None
+    }
+
+    let mut guards = Vec::new();
+    let mut index = 1;
+
+    while index + 1 < line.len() && *line[index] == Type(Word) && *line[index + 1] == Type(Word) {
+        match line[index].lexeme.unwrap() {
+            "if"     => guards.push((true, line[index + 1])),
+            "unless" => guards.push((false, line[index + 1])),
+
+            _ => return None,
+        }
+
+        index += 2;
+    }
+
+    if index < line.len() && *line[index] == Pair(Symbol, ":") {
+        Some((line[0].lexeme.unwrap(), guards, &line[index + 1..]))
+    } else {
+        None
+    }
+}
+
+/// reads the `alias, "message"` arguments of a diagnostic action
+fn diagnostic<'t, 's> (args: &[&'t Token<'s>]) -> Option<(&'s str, &'t str)> {
+    if args.len() == 3
+    && *args[0] == Type(Word) && *args[1] == Pair(Symbol, ",") && *args[2] == Type(Str) {
+        Some((args[0].lexeme.unwrap(), args[2].value.as_ref().unwrap()))
+    } else {
+        None
+    }
+}
+
+/// replaces `{name}` placeholders in output text, `\` escapes the next character,
+/// `{name, "sep"}` joins the matches of a repeated alias with a separator
+fn substitute<F> (text: &str, mut resolve: F) -> String
+    where F: FnMut(&str, &str) -> Option<String> {
+
+    let mut result = String::new();
+    let mut last = 0;
+
+    for (from, to, name, separator) in placeholders(text) {
+        result.push_str(&unescape(&text[last..from]));
+
+        match resolve(name, &unescape(separator.unwrap_or(""))) {
+            Some(value) => result.push_str(&value),
+            None        => result.push_str(&text[from..to]),
+        }
+
+        last = to;
+    }
+
+    result.push_str(&unescape(&text[last..]));
+    result
+}
+
+/// finds the `{name}`, `{name, "sep"}` and `{@comments}` placeholders in output text,
+/// as the byte range of each along with the name and separator
+fn placeholders (text: &str) -> Vec<(usize, usize, &str, Option<&str>)> {
+    let mut found = Vec::new();
+    let mut iter = text.char_indices();
+
+    while let Some((from, ch)) = iter.next() {
+        match ch {
+            '\\' => {
+                iter.next();
+            },
+
+            '{' => {
+                let rest = &text[from + 1..];
+
+                // comments are referred to as `{@comments}`, apart from aliases
+                let sigil = rest.starts_with('@') as usize;
+                let len = sigil + rest[sigil..].find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(rest.len() - sigil);
+
+                if len == sigil {
+                    continue
+                }
+
+                let (separator, end) = match parse_separator(&rest[len..]) {
+                    Some((separator, end)) => (Some(separator), len + end),
+                    None if rest[len..].starts_with('}') => (None, len + 1),
+                    None => continue,
+                };
+
+                found.push((from, from + end + 1, &rest[..len], separator));
+                iter.nth(rest[..end].chars().count() - 1);
+            },
+
+            _ => (),
+        }
+    }
+
+    found
+}
+
+/// reads the `, "sep"}` ending a placeholder, along with the length it takes up
+fn parse_separator (text: &str) -> Option<(&str, usize)> {
+    let trimmed = text.trim_start();
+    let rest = trimmed.strip_prefix(',')?.trim_start().strip_prefix('"')?;
+
+    let mut iter = rest.char_indices();
+
+    while let Some((index, ch)) = iter.next() {
+        match ch {
+            '\\' => {
+                iter.next();
+            },
+
+            '"' => {
+                let after = &rest[index + 1..];
+                let close = after.len() - after.trim_start().len();
+
+                if after[close..].starts_with('}') {
+                    return Some((&rest[..index], text.len() - after.len() + close + 1))
+                }
+
+                return None
+            },
+
+            _ => (),
+        }
+    }
+
+    None
+}
+
+fn unescape (text: &str) -> String {
+    let mut result = String::new();
+    let mut iter = text.chars();
+
+    while let Some(ch) = iter.next() {
+        match ch {
+            '\\' => if let Some(escaped) = iter.next() {
+                result.push(escaped)
+            },
+
+            _ => result.push(ch),
+        }
+    }
+
+    result
 }
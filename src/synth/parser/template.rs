@@ -4,6 +4,8 @@ use tokenizer::token::Type::*;
 use tokenizer::tokenizer::*;
 use rule::*;
 use alias::*;
+use extras::string::StringExtras;
+use error::*;
 
 
 #[derive(Debug)]
@@ -18,7 +20,7 @@ impl<'t, 's: 't> Template<'t, 's> {
         Self { source, rules:None }
     }
 
-    pub fn find_rule (&self, name: &str) -> Option<&Rule> {
+    pub fn find_rule (&self, name: &str) -> Option<&Rule<'t, 's>> {
         for rule in self.rules.as_ref().unwrap().iter() {
             if rule.name.lexeme.unwrap() == name {
                 return Some(rule)
@@ -28,25 +30,250 @@ impl<'t, 's: 't> Template<'t, 's> {
         None
     }
 
-    pub fn parse (&mut self) {
+    /// whether any segment carries comments over with a `{@comments}` placeholder
+    pub fn keeps_comments (&self) -> bool {
+        self.rules.as_ref().unwrap().iter().any(|rule| {
+            rule.segments.iter()
+                .chain(rule.variants.iter().flat_map(|variant| variant.segments.iter()))
+                .any(|segment| segment.references(self.source).iter().any(|&(name, _)| name == "@comments"))
+        })
+    }
+
+    pub fn parse (&mut self) -> CompileResult<'s, (), ()> {
         let tokens = self.source.tokens.as_ref().unwrap();
 
         let mut iter = TokenIterator::new(tokens);
         let mut rules = Vec::new();
+        let mut response = Vec::new();
 
         while !iter.match_with(&[Type(EOF)]) {
             if iter.check(&[Type(Word), Pair(Symbol, ":"), Type(EOL)])
             || iter.check(&[Type(Word), Pair(Symbol, "!"), Type(EOL)]) {
-                rules.push(self.parse_rule(&mut iter));
-                println!("");
+                if let Some(rule) = self.parse_rule(&mut iter, &mut response) {
+                    rules.push(rule);
+                }
+
+            } else {
+                response.push(Response::Error("expected rule definition".into(), iter.get(0).unwrap().position()));
+                iter.skip_line();
             }
-            //iter.next();
         }
 
         self.rules = Some(rules);
+
+        if response.is_empty() {
+            self.validate(&mut response);
+        }
+
+        if !response.is_empty() {
+            Err(Outcome::new((), Some(response)))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// checks that every rule and type a pattern refers to exists, that segment
+    /// actions can be read and that segments only refer to what their variants bind
+    fn validate (&self, response: &mut Vec<Response<'s>>) {
+        let nullable = self.nullable();
+
+        for rule in self.rules.as_ref().unwrap().iter() {
+            for variant in rule.variants.iter() {
+                for (index, alias) in variant.aliases.iter().enumerate() {
+                    // aliases in different branches of a group are never bound together
+                    if variant.aliases[..index].iter().any(|a| a.name.lexeme == alias.name.lexeme && !variant.exclusive(a.token, alias.token)) {
+                        response.push(Response::Error("duplicate alias".into(), alias.name.position()));
+                    }
+                }
+
+                if rule.is_matching && !rule.is_binary(variant) && self.leading(variant, (0, variant.tokens.len()), &[], &mut Vec::new()) {
+                    response.push(Response::Error("variant of a matching rule can match nothing".into(), variant.name.position()));
+                }
+
+                for segment in variant.segments.iter() {
+                    segment.validate(response);
+                    self.validate_segment(segment, variant, response);
+                }
+
+                // rule segments apply to variants not declaring their own
+                for segment in rule.segments.iter() {
+                    if variant.segment(segment.name.lexeme.unwrap()).is_none() {
+                        self.validate_segment(segment, variant, response);
+                    }
+                }
+
+                for (index, token) in variant.tokens.iter().enumerate() {
+                    // escaped words are matched literally
+                    if token.token_type != Word || (index > 0 && variant.tokens[index - 1].lexeme == Some("\\")) {
+                        continue
+                    }
+
+                    let name = token.lexeme.unwrap();
+                    let aliased = variant.aliases.iter().any(|a| a.token == index);
+
+                    if name.is_uppercase() {
+                        if Type::from_str(name).is_none() {
+                            response.push(Response::Error("undefined type".into(), token.position()));
+                        }
+
+                    } else if aliased && self.find_rule(name).is_none() {
+                        response.push(Response::Error("undefined rule".into(), token.position()));
+                    }
+                }
+            }
+
+            for segment in rule.segments.iter() {
+                segment.validate(response);
+            }
+
+            if !rule.operators.is_empty() && !rule.variants.iter().any(|v| v.is_binary()) {
+                response.push(Response::Error(
+                    "operator table needs a binary variant, as in `l:rule o:op r:rule`".into(),
+                    rule.name.position(),
+                ));
+            }
+
+            if let Some(cycle) = self.left_recursion(rule, rule, &nullable, &mut Vec::new()) {
+                response.push(Response::Error(
+                    format!("left recursive rule, {}", cycle.join(" -> ")).into(),
+                    rule.name.position(),
+                ));
+            }
+        }
+    }
+
+    /// names of the rules that can match without consuming anything,
+    /// added until no rule left has a variant matching nothing
+    fn nullable (&self) -> Vec<&'s str> {
+        let mut nullable = Vec::new();
+
+        loop {
+            let found: Vec<&'s str> = self.rules.as_ref().unwrap().iter()
+                .filter(|rule| !nullable.contains(&rule.name.lexeme.unwrap()))
+                .filter(|rule| rule.variants.iter()
+                    .filter(|v| !rule.is_binary(v))
+                    .any(|v| self.leading(v, (0, v.tokens.len()), &nullable, &mut Vec::new())))
+                .map(|rule| rule.name.lexeme.unwrap())
+                .collect();
+
+            if found.is_empty() {
+                return nullable
+            }
+
+            nullable.extend(found);
+        }
+    }
+
+    /// rules a variant of this rule can start by matching
+    fn leftmost (&self, rule: &Rule<'t, 's>, nullable: &[&'s str]) -> Vec<&Rule<'t, 's>> {
+        let mut rules = Vec::new();
+
+        // binary variants are matched by precedence, from an operand onwards
+        for variant in rule.variants.iter().filter(|v| !rule.is_binary(v)) {
+            self.leading(variant, (0, variant.tokens.len()), nullable, &mut rules);
+        }
+
+        rules
+    }
+
+    /// collects the rules a range of a pattern can start with,
+    /// returns whether the range can match without consuming anything
+    fn leading<'a> (
+        &'a self, variant: &Variant<'t, 's>, range: (usize, usize), nullable: &[&'s str], rules: &mut Vec<&'a Rule<'t, 's>>
+    ) -> bool {
+        let mut index = range.0;
+
+        while index < range.1 {
+            if let Some(group) = variant.group(index) {
+                let mut empty = group.repeat.min == 0;
+
+                for &branch in group.branches.iter() {
+                    empty |= self.leading(variant, branch, nullable, rules);
+                }
+
+                if !empty {
+                    return false
+                }
+
+                index = group.end + 1;
+                continue
+            }
+
+            // elements matching zero times leave the next one leftmost
+            let alias = match variant.alias(index) {
+                Some(alias) => alias,
+                None        => return false,
+            };
+
+            let name = variant.tokens[index].lexeme.unwrap();
+
+            if !name.is_uppercase() {
+                if let Some(found) = self.find_rule(name) {
+                    rules.push(found);
+                }
+            }
+
+            if alias.repeat.min > 0 && !nullable.contains(&name) {
+                return false
+            }
+
+            index += 1;
+        }
+
+        true
+    }
+
+    /// finds a chain of leftmost rules leading from `rule` back to `target`
+    fn left_recursion (
+        &self, target: &Rule<'t, 's>, rule: &Rule<'t, 's>, nullable: &[&'s str], path: &mut Vec<&'s str>
+    ) -> Option<Vec<&'s str>> {
+
+        path.push(rule.name.lexeme.unwrap());
+
+        for next in self.leftmost(rule, nullable) {
+            if next.name.lexeme == target.name.lexeme {
+                let mut cycle = path.clone();
+                cycle.push(next.name.lexeme.unwrap());
+                return Some(cycle)
+            }
+
+            if !path.contains(&next.name.lexeme.unwrap()) {
+                if let Some(cycle) = self.left_recursion(target, next, nullable, path) {
+                    return Some(cycle)
+                }
+            }
+        }
+
+        path.pop();
+        None
     }
 
-    fn parse_rule (&mut self, mut iter: &mut TokenIterator<'t, 's>) -> Rule<'t, 's> {
+    fn validate_segment (&self, segment: &Segment<'t, 's>, variant: &Variant<'t, 's>, response: &mut Vec<Response<'s>>) {
+        for (name, position) in segment.references(self.source) {
+            match name.strip_prefix('@') {
+                Some("comments") | Some("trailing") => (),
+                Some(_) => response.push(Response::Error("unknown comment placeholder".into(), position)),
+
+                None if !variant.binds(name) => response.push(Response::Error("undefined alias".into(), position)),
+
+                // a rule name stands for the child matched through its alias, of which there has to be one
+                None if !variant.aliases.iter().any(|a| a.name.lexeme == Some(name)) && variant.targets(name) > 1 => {
+                    response.push(Response::Error(
+                        format!("ambiguous reference to rule {}, use one of its aliases", name).into(), position
+                    ))
+                },
+
+                None => (),
+            }
+        }
+    }
+
+    fn parse_rule (
+        &mut self,
+        iter: &mut TokenIterator<'t, 's>,
+        response: &mut Vec<Response<'s>>,
+    ) -> Option<Rule<'t, 's>> {
+
         let name = iter.next().unwrap();
         let is_matching = iter.check(&[Pair(Symbol, ":")]);
         iter.eat(2);
@@ -56,95 +283,213 @@ impl<'t, 's: 't> Template<'t, 's> {
 
 
         if !iter.match_with(&[Type(Indent)]) {
-            panic!("empty rule");
+            response.push(Response::Error("empty rule".into(), name.position()));
+            return None
         }
 
-        println!(":: parsing rule {:?}", name.lexeme);
-
         while !iter.match_with(&[Type(Dedent)]) {
-            if iter.check(&[Type(Word), Lexeme(":"), Lexeme("=")]) {
-                variants.push(self.parse_variant(&mut iter, name.lexeme.unwrap()));
+            if iter.check(&[Type(EOF)]) {
+                break
+
+            } else if iter.check(&[Type(Word), Lexeme(":"), Lexeme("=")]) || iter.check(&[Type(Word), Lexeme(":=")]) {
+                if let Some(variant) = self.parse_variant(iter, name.lexeme.unwrap(), response) {
+                    variants.push(variant);
+                }
 
             } else if iter.check(&[Lexeme("["), Type(Word), Lexeme("]"), Type(EOL)]) {
-                segments.push(self.parse_segment(&mut iter, name.lexeme.unwrap(), None));
+                if let Some(segment) = self.parse_segment(iter, response) {
+                    segments.push(segment);
+                }
+
+            } else {
+                response.push(Response::Error("expected variant or segment".into(), iter.get(0).unwrap().position()));
+                iter.skip_line();
+            }
+        }
+
+        let operators = match segments.iter().position(|s| s.name.lexeme == Some("operators")) {
+            Some(index) => self.parse_operators(&segments.remove(index), response),
+            None        => Vec::new(),
+        };
+
+        Some(Rule::new ( name, is_matching, variants, segments, operators ))
+    }
+
+    /// reads an `[operators]` table, a line per precedence level from the loosest
+    /// binding, each naming its associativity and the operators on it, as in `left: + -`
+    fn parse_operators (&self, segment: &Segment<'t, 's>, response: &mut Vec<Response<'s>>) -> Vec<Operator<'s>> {
+        let mut operators: Vec<Operator<'s>> = Vec::new();
+
+        for (level, line) in segment.lines().iter().enumerate() {
+            if line.len() < 3 || *line[0] != Type(Word) || *line[1] != Pair(Symbol, ":") {
+                response.push(Response::Error("expected operator level".into(), line[0].position()));
+                continue
+            }
+
+            let assoc = match Assoc::from_str(line[0].lexeme.unwrap()) {
+                Some(assoc) => assoc,
+                None => {
+                    response.push(Response::Error(
+                        "expected left, right or nonassoc".into(), line[0].position()
+                    ));
+                    continue
+                },
+            };
+
+            for symbol in self.source.span(line[2], line[line.len() - 1]).split_whitespace() {
+                if operators.iter().any(|op| op.symbol == symbol) {
+                    response.push(Response::Error(format!("duplicate operator {}", symbol).into(), line[0].position()));
+                }
+
+                operators.push(Operator { symbol, level, assoc });
             }
         }
 
-        Rule::new ( name, is_matching, variants, segments )
+        operators
     }
 
     fn parse_variant (
         &mut self,
-        mut iter: &mut TokenIterator<'t, 's>,
+        iter: &mut TokenIterator<'t, 's>,
         rule: &'t str,
-    ) -> Variant<'t, 's> {
+        response: &mut Vec<Response<'s>>,
+    ) -> Option<Variant<'t, 's>> {
 
         let name = iter.get(0).unwrap();
-        iter.eat(3);
 
-        println!("   := parsing variant {:?}", name.lexeme);
+        // `:=` is a single token when declared in `!/def/symbols`
+        if iter.check(&[Type(Word), Lexeme(":=")]) {
+            iter.eat(2);
+        } else {
+            iter.eat(3);
+        }
 
         let mut segments: Vec<Segment<'t, 's>> = Vec::new();
         let mut tokens: Vec<&'t Token<'s>> = Vec::new();
         let mut aliases: Vec<Alias<'t, 's>> = Vec::new();
+        let mut groups: Vec<Group<'t, 's>> = Vec::new();
+
+        let mut valid = true;
 
         if iter.match_with(&[Type(EOL)]) {
-            panic!("variant has no pattern");
+            response.push(Response::Error("variant has no pattern".into(), name.position()));
+            iter.skip_block();
+            return None
         }
 
+        // groups still open, with the branches closed in them so far
+        let mut open: Vec<(usize, Vec<(usize, usize)>)> = Vec::new();
+
         while !iter.match_with(&[Type(EOL)]) {
+            let mut alias_name = None;
+
             if iter.check(&[Type(Word), Pair(Symbol, ":")]) {
-                let alias_name = iter.next().unwrap();
+                alias_name = iter.next();
                 iter.next();
 
+                let elem = iter.get(0).unwrap();
+
                 if !iter.check(&[Type(Word)]) {
-                    panic!("can't alias non-word");
+                    response.push(Response::Error("can't alias non-word".into(), elem.position()));
+                    valid = false;
                 }
+            }
+
+            if iter.check(&[Type(EOF)]) {
+                break
 
-                let elem = iter.get(0).unwrap().lexeme.unwrap();
+            } else if iter.check(&[Type(EOL)]) {
+                continue
+            }
+
+            let elem = iter.next().unwrap();
+            tokens.push(elem);
 
-                if elem.to_string().to_uppercase() == elem.to_string() && Type::from_str(elem) == None {
-                    panic!("undefined type");
+            if *elem == Pair(Symbol, "\\") {
+                // the escaped element is kept as is
+                if !iter.check(&[Type(EOL)]) && !iter.check(&[Type(EOF)]) {
+                    tokens.push(iter.next().unwrap());
                 }
 
-                //println!("   {}:{}", alias_name.lexeme.unwrap(), elem);
+            } else if *elem == Pair(Symbol, "(") {
+                open.push((tokens.len() - 1, Vec::new()));
+
+            } else if *elem == Pair(Symbol, "|") && !open.is_empty() {
+                let (start, ref mut branches) = *open.last_mut().unwrap();
+                let from = branches.last().map_or(start, |b| b.1) + 1;
+
+                branches.push((from, tokens.len() - 1));
+
+            } else if *elem == Pair(Symbol, ")") {
+                let (start, mut branches) = match open.pop() {
+                    Some(group) => group,
+                    None => {
+                        response.push(Response::Error("unmatched parenthesis".into(), elem.position()));
+                        valid = false;
+                        continue
+                    },
+                };
+
+                let end = tokens.len() - 1;
+                let from = branches.last().map_or(start, |b| b.1) + 1;
+
+                branches.push((from, end));
+
+                match parse_repeat(iter, elem, response) {
+                    Some(repeat) => groups.push(Group { start, end, branches, repeat }),
+                    None         => valid = false,
+                }
 
-                aliases.push(Alias::new(alias_name, tokens.len()));
+            } else if let Some(alias_name) = alias_name {
+                match parse_repeat(iter, elem, response) {
+                    Some(repeat) => aliases.push(Alias::repeated(alias_name, tokens.len() - 1, repeat)),
+                    None         => valid = false,
+                }
             }
+        }
 
-            tokens.push(iter.next().unwrap());
+        for &(start, _) in open.iter() {
+            response.push(Response::Error("unclosed parenthesis".into(), tokens[start].position()));
+            valid = false;
         }
 
         if iter.match_with(&[Type(Indent)]) {
-            loop {
-                if iter.check(&[Pair(Symbol, "["), Type(Word), Pair(Symbol, "]"), Type(EOL)]) {
-                    print!("   ");
-                    segments.push(self.parse_segment(&mut iter, &rule, Some(name.lexeme.unwrap())));
-                }
-
-                if iter.match_with(&[Type(Dedent)]) {
+            while !iter.match_with(&[Type(Dedent)]) {
+                if iter.check(&[Type(EOF)]) {
                     break
+
+                } else if iter.check(&[Pair(Symbol, "["), Type(Word), Pair(Symbol, "]"), Type(EOL)]) {
+                    if let Some(segment) = self.parse_segment(iter, response) {
+                        segments.push(segment);
+                    }
+
+                } else {
+                    response.push(Response::Error("expected segment".into(), iter.get(0).unwrap().position()));
+                    iter.skip_line();
                 }
             }
         }
 
-        Variant::new ( name, &rule, tokens, segments, aliases )
+        if valid {
+            Some(Variant::new ( name, rule, tokens, segments, aliases, groups ))
+        } else {
+            None
+        }
     }
 
     fn parse_segment (
         &mut self,
         iter: &mut TokenIterator<'t, 's>,
-        rule: &'t str,
-        variant: Option<&'t str>,
-    ) -> Segment<'t, 's> {
+        response: &mut Vec<Response<'s>>,
+    ) -> Option<Segment<'t, 's>> {
 
         let name = iter.get(1).unwrap();
         iter.eat(3);
 
-        println!("   [] parsing segment {:?}", name.lexeme);
-
         if !iter.match_with(&[Type(EOL), Type(Indent)]) {
-            panic!("empty segment");
+            response.push(Response::Error("empty segment".into(), name.position()));
+            iter.match_with(&[Type(EOL)]);
+            return None
         }
 
         let mut tokens: Vec<&'t Token<'s>> = Vec::new();
@@ -164,6 +509,48 @@ impl<'t, 's: 't> Template<'t, 's> {
 
         iter.next();
 
-        Segment::new ( name, rule, variant, tokens )
+        Some(Segment::new ( name, tokens ))
+    }
+}
+
+/// reads the quantifier directly following an aliased element,
+/// and a `%` separator after it, a bare separator repeats zero or more times
+fn parse_repeat<'t, 's> (
+    iter: &mut TokenIterator<'t, 's>,
+    elem: &Token<'s>,
+    response: &mut Vec<Response<'s>>,
+) -> Option<Repeat<'t, 's>> {
+
+    let mut repeat = Repeat::once();
+
+    if let Some(next) = iter.get(0) {
+        let adjacent = next.token_type == Symbol && next.line == elem.line && next.slice.0 == elem.slice.1;
+
+        if let Some(found) = Repeat::from_str(next.lexeme.unwrap_or("")).filter(|_| adjacent) {
+            repeat = found;
+            iter.next();
+        }
+    }
+
+    if iter.check(&[Pair(Symbol, "%")]) {
+        let percent = iter.next().unwrap();
+
+        if iter.check(&[Type(EOL)]) || iter.check(&[Type(EOF)]) {
+            response.push(Response::Error("expected separator".into(), percent.position()));
+            return None
+        }
+
+        if repeat.max == Some(1) && repeat.min == 0 {
+            response.push(Response::Error("optional element can't be separated".into(), percent.position()));
+            return None
+        }
+
+        if !repeat.is_list() {
+            repeat = Repeat::from_str("*").unwrap();
+        }
+
+        repeat.separator = iter.next();
     }
+
+    Some(repeat)
 }
@@ -6,227 +6,400 @@ use template::*;
 use alias::*;
 use rule::*;
 use std::sync::Arc;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::collections::HashMap;
 use extras::string::{StringExtras};
 use compiler::compiler::AST;
+use error::*;
 
 #[derive(Debug)]
 pub struct Node<'u> {
     pub variant: &'u Variant<'u, 'u>,
     pub tokens: Vec<Alias<'u, 'u>>,
-    pub children: Vec<Node<'u>>,
+    pub children: Vec<Child<'u>>,
+
+    // range of unit tokens the node matched
+    pub span: (usize, usize),
 }
 
+/// a node matched through a rule alias, shared between the
+/// memo and every variant that tried the same rule at that token
 #[derive(Debug)]
-pub struct Path<'u> {
-    variant: &'u Variant<'u, 'u>,
-    children: Vec<Path<'u>>,
+pub struct Child<'u> {
+    pub alias: &'u Token<'u>,
+    pub node: Rc<Node<'u>>,
 }
 
 #[derive(Debug)]
 pub struct Unit<'u> {
     source: &'u Source<'u>,
     template: Arc<Template<'u, 'u>>,
-}
 
-fn dump_path(path: &Path) {
-    print!("{}", path.variant.name.lexeme.unwrap());
-    for path in path.children.iter() {
-        print!(" -> ");
-        dump_path(&path);
-    }
+    // furthest token any variant matched up to, for reporting
+    furthest: Cell<usize>,
 }
 
+// outcome of matching a rule at a token
+type Memo<'p> = HashMap<(&'p str, usize), Option<Rc<Node<'p>>>>;
+
 impl<'u> Unit<'u> {
     pub fn new (source: &'u Source<'u>, template: Arc<Template<'u,'u>>) -> Self  {
-        Self { source, template }
+        Self { source, template, furthest: Cell::new(0) }
     }
 
-    pub fn parse (&mut self) -> AST {
+    pub fn parse (&mut self) -> CompileResult<'u, AST<'_>, ()> {
         let tokens = self.source.tokens.as_ref().unwrap();
 
         let mut source = TokenIterator::new(tokens);
-        let mut paths: Vec<Path> = Vec::new();
-        let mut nodes: Vec<Node> = Vec::new();
+        let mut nodes: Vec<Rc<Node>> = Vec::new();
+        let mut response = Vec::new();
+        let mut memo = Memo::new();
+
+        while source.get(0).is_some() {
+            let mut node: Option<Rc<Node>> = None;
 
-        while source.get(0) != None {
-            let mut path: Option<Path> = None;
+            self.furthest.set(source.current);
 
             for rule in self.template.rules.as_ref().unwrap().iter() {
                 if rule.is_matching {
-                    path = self.check_rule(&mut source, &rule);
-                    if let Some(_) = path {
+                    // a match taking up no tokens would be made again and again
+                    node = self.check_rule(&mut source, rule, &mut memo).filter(|node| node.span.0 < node.span.1);
+                    if node.is_some() {
                         break
                     }
                 }
             }
 
-            if let None = path {
-                if source.get(0).unwrap() == &Type(Type::EOF) {
-                    break
-                }
-                panic!("no path matches at token {:?}", source.get(0));
+            match node {
+                Some(node) => nodes.push(node),
+
+                None => {
+                    if source.get(0).unwrap() == &Type(Type::EOF) {
+                        break
+                    }
+
+                    response.append(&mut self.unmatched(&source));
+                    source.skip_line();
+                },
             }
+        }
 
-            println!("   ++ matched {}", path.as_ref().unwrap().variant.name.lexeme.unwrap());
-            paths.push(path.unwrap());
+        if !response.is_empty() {
+            return Err(Outcome::new((), Some(response)))
         }
 
-        print!("\n:: PATHS ::\n   ");
+        Ok(AST::new ( self.source, &self.template, nodes ))
+    }
 
-        for path in paths.iter() {
-            dump_path(&path);
-            print!("\n   ");
+    /// describes why matching failed at the current token
+    fn unmatched (&self, source: &TokenIterator) -> Vec<Response<'u>> {
+        let tokens = self.source.tokens.as_ref().unwrap();
+        let token = source.get(0).unwrap();
+
+        let tried: Vec<&str> = self.template.rules.as_ref().unwrap().iter()
+            .filter(|r| r.is_matching)
+            .map(|r| r.name.lexeme.unwrap())
+            .collect();
+
+        let mut response = vec![Response::Error(
+            format!("no rule matches, tried {}", tried.join(", ")).into(),
+            token.position(),
+        )];
+
+        if self.furthest.get() > source.current {
+            response.push(Response::Note(
+                "furthest match got up to here".into(),
+                tokens[self.furthest.get()].position(),
+            ));
         }
 
-        println!("\n:: AST ::");
-        source.current = 0;
+        response
+    }
 
-        for path in paths.iter() {
-            nodes.push(self.parse_path(&mut source, path));
+    /// resets the source after a failed match, remembering how far it got
+    fn fail (&self, source: &mut TokenIterator, reset: usize) -> bool {
+        if source.current > self.furthest.get() {
+            self.furthest.set(source.current);
         }
 
-        AST::new ( self.source, nodes )
+        source.current = reset;
+        false
     }
 
-    fn check_rule (
-        &self, mut source: &mut TokenIterator, rule: &'u Rule
-    ) -> Option<Path> {
-        for variant in &rule.variants {
-            if let Some(path) = self.check_variant(&mut source, variant) {
-                return Some(path)
+    fn check_rule<'p> (
+        &'p self, source: &mut TokenIterator, rule: &'p Rule<'u, 'u>, memo: &mut Memo<'p>
+    ) -> Option<Rc<Node<'p>>> {
+        let key = (rule.name.lexeme.unwrap(), source.current);
+
+        if let Some(result) = memo.get(&key).cloned() {
+            if let Some(ref node) = result {
+                source.current = node.span.1;
+            }
+
+            return result
+        }
+
+        // reaching the same rule at the same token again before it finishes fails,
+        // so left recursion can't recurse forever
+        memo.insert(key, None);
+
+        let result = if rule.operators.is_empty() {
+            self.check_operand(source, rule, memo)
+        } else {
+            self.check_operation(source, rule, 0, memo)
+        };
+
+        memo.insert(key, result.clone());
+        result
+    }
+
+    /// matches the first variant of a rule that matches on its own
+    fn check_operand<'p> (
+        &'p self, source: &mut TokenIterator, rule: &'p Rule<'u, 'u>, memo: &mut Memo<'p>
+    ) -> Option<Rc<Node<'p>>> {
+        for variant in rule.variants.iter().filter(|v| !rule.is_binary(v)) {
+            if let Some(node) = self.check_variant(source, variant, memo) {
+                return Some(Rc::new(node))
             }
         }
 
         None
     }
 
-    fn check_variant (
-        &self, mut source: &mut TokenIterator, variant: &'u Variant
-    ) -> Option<Path> {
-        //println!("?? checking {}", &variant.name.lexeme.unwrap());
+    /// matches operands joined by operators of at least the given level,
+    /// nesting them by the precedence and associativity in the operator table
+    fn check_operation<'p> (
+        &'p self, source: &mut TokenIterator, rule: &'p Rule<'u, 'u>, min: usize, memo: &mut Memo<'p>
+    ) -> Option<Rc<Node<'p>>> {
+
+        let start = source.current;
+        let mut left = self.check_operand(source, rule, memo)?;
 
-        let aliases = &variant.aliases;
-        let tokens = &variant.tokens;
+        // level of the last non associative operator, which can't be chained
+        let mut nonassoc = None;
 
-        let mut children: Vec<Path> = Vec::new();
+        while let Some((variant, operator, op)) = self.check_operator(source, rule, memo) {
+            let before = op.span.0;
 
-        let reset = source.current;
-        let mut alias = 0;
-        let mut index = 0;
+            if operator.level < min || nonassoc == Some(operator.level) {
+                source.current = before;
+                break
+            }
 
-        while index < tokens.len() {
-            //println!("{:?}", &tokens[index]);
+            let next = match operator.assoc {
+                Assoc::Right => operator.level,
+                _            => operator.level + 1,
+            };
 
-            if tokens[index].lexeme.unwrap() == "\\" {
-                index += 1;
+            let right = match self.check_operation(source, rule, next, memo) {
+                Some(right) => right,
+                None => {
+                    source.current = before;
+                    break
+                },
+            };
+
+            if operator.assoc == Assoc::Nonassoc {
+                nonassoc = Some(operator.level);
             }
 
-            if alias < aliases.len() && index == aliases[alias].token {
-                if tokens[index].lexeme.unwrap().is_uppercase() {
-                    if source.get(0).unwrap()
-                    != &Type(Type::from_str(tokens[index].lexeme.unwrap()).unwrap()) {
-                        //println!("   -- didn't match type {}", tokens[index].lexeme.unwrap());
-                        source.current = reset;
-                        return None
-
-                    } else {
-                        index += 1;
-                        source.next();
-                    }
+            let children = vec![
+                Child { alias: variant.aliases[0].name, node: left },
+                Child { alias: variant.aliases[1].name, node: op },
+                Child { alias: variant.aliases[2].name, node: right },
+            ];
 
-                } else {
-                    let rules = self.template.rules.as_ref();
-                    let recurse = rules
-                        .unwrap()
-                        .iter()
-                        .find(|&r| r.name.lexeme == tokens[index].lexeme)
-                        .unwrap();
+            left = Rc::new(Node { variant, tokens: Vec::new(), children, span: (start, source.current) });
+        }
 
-                    //println!("   @@ {:?}", recurse.name);
+        Some(left)
+    }
 
-                    //source.eat(index);
-                    let rule = self.check_rule(&mut source, recurse);
+    /// matches the operator rule of a binary variant,
+    /// as long as what it matched is in the operator table
+    fn check_operator<'p> (
+        &'p self, source: &mut TokenIterator, rule: &'p Rule<'u, 'u>, memo: &mut Memo<'p>
+    ) -> Option<(&'p Variant<'u, 'u>, &'p Operator<'u>, Rc<Node<'p>>)> {
 
-                    if let Some(r) = rule {
-                        index += 1;
-                        children.push(r);
+        let tokens = self.source.tokens.as_ref().unwrap();
+        let before = source.current;
 
-                    } else {
-                        //println!("   -- didn't match rule {}", recurse.name.lexeme.unwrap());
-                        source.current = reset;
-                        return None
-                    }
-                }
-                alias += 1;
+        for variant in rule.variants.iter().filter(|v| rule.is_binary(v)) {
+            let op_rule = self.template.find_rule(variant.tokens[1].lexeme.unwrap()).unwrap();
 
-            } else {
-                /*
-                println!("   !! {:?} {}", tokens[index].lexeme, source.get(0).unwrap().lexeme.unwrap_or(
-                   &source.get(0).unwrap().token_type.to_str()
-                ));
-                */
-                if tokens[index].token_type == Word && tokens[index].lexeme.unwrap().is_uppercase() {
-                    if source.get(0).unwrap()
-                    != &Type(Type::from_str(tokens[index].lexeme.unwrap()).unwrap()) {
-                        //println!("   -- didn't match type {}", tokens[index].lexeme.unwrap());
-                        source.current = reset;
-                        return None
-                    }
+            let node = match self.check_rule(source, op_rule, memo) {
+                Some(node) => node,
+                None       => continue,
+            };
 
-                } else if source.get(0).unwrap() != &Lexeme(tokens[index].lexeme.unwrap()) {
-                    /*
-                    println!("   -- didn't match token {} at {}",
-                        tokens[index].lexeme.unwrap(),
-                        &source.get(0).unwrap().lexeme.unwrap_or(&source.get(0).unwrap().token_type.to_str())
-                    );
-                    */
-                    source.current = reset;
-                    return None
-                }
+            // an operator is written on one line, even where brackets join lines
+            if node.span.1 > node.span.0 && tokens[node.span.0].line == tokens[node.span.1 - 1].end_line {
+                let symbol = self.source.span(&tokens[node.span.0], &tokens[node.span.1 - 1]);
 
-                index += 1;
-                source.next();
+                if let Some(operator) = rule.operator(symbol) {
+                    return Some((variant, operator, node))
+                }
             }
+
+            source.current = before;
         }
 
-        //source.eat(index);
-        Some(Path{ variant: variant, children: children })
+        None
     }
 
-    fn parse_path (&self, mut source: &mut TokenIterator, path: &Path<'u>) -> Node {
-        let variant = &path.variant;
+    /// matches a variant pattern at the current token, binding aliased
+    /// tokens and the nodes of aliased rules as it goes
+    fn check_variant<'p> (
+        &'p self, source: &mut TokenIterator, variant: &'p Variant<'u, 'u>, memo: &mut Memo<'p>
+    ) -> Option<Node<'p>> {
 
-        let mut children: Vec<Node> = Vec::new();
         let mut tokens: Vec<Alias> = Vec::new();
+        let mut children: Vec<Child> = Vec::new();
 
-        let mut alias = 0;
-        let mut index = 0;
+        let start = source.current;
+        let range = (0, variant.tokens.len());
 
-        let mut pchildren = path.children.iter();
+        if self.check_sequence(source, variant, range, &mut tokens, &mut children, memo) {
+            Some(Node { variant, tokens, children, span: (start, source.current) })
+        } else {
+            None
+        }
+    }
 
-        while index < variant.tokens.len() {
-            if variant.tokens[index].lexeme.unwrap() == "\\" {
+    /// matches a range of pattern elements one after another, resetting
+    /// the source and dropping what was bound if any of them fails
+    fn check_sequence<'p> (
+        &'p self,
+        source: &mut TokenIterator,
+        variant: &'p Variant<'u, 'u>,
+        range: (usize, usize),
+        tokens: &mut Vec<Alias<'p, 'p>>,
+        children: &mut Vec<Child<'p>>,
+        memo: &mut Memo<'p>,
+    ) -> bool {
+
+        let reset = (source.current, tokens.len(), children.len());
+        let mut index = range.0;
+
+        while index < range.1 {
+            // escaped elements are always matched literally
+            let escaped = variant.tokens[index].lexeme == Some("\\") && index + 1 < range.1;
+
+            if escaped {
                 index += 1;
             }
 
-            if alias < variant.aliases.len() && index == variant.aliases[alias].token {
-                if variant.tokens[index].lexeme.unwrap().is_uppercase() {
-                    tokens.push(Alias::new(variant.aliases[alias].name, source.current));
-                    source.next();
+            let elem = variant.tokens[index];
 
-                } else {
-                    children.push(self.parse_path(&mut source, pchildren.next().unwrap()));
-                }
+            let matched = if let Some(group) = variant.group(index) {
+                index = group.end;
+
+                check_repeat(source, &group.repeat, |source| {
+                    group.branches.iter().any(|&branch| self.check_sequence(source, variant, branch, tokens, children, memo))
+                })
 
-                alias += 1;
+            } else if let Some(alias) = variant.alias(index) {
+                check_repeat(source, &alias.repeat, |source| {
+                    self.check_element(source, elem, alias, tokens, children, memo)
+                })
 
             } else {
-                source.next();
+                check_literal(source, elem, escaped)
+            };
+
+            if !matched {
+                tokens.truncate(reset.1);
+                children.truncate(reset.2);
+                return self.fail(source, reset.0)
             }
 
             index += 1;
         }
 
-        Node { variant, tokens, children }
+        true
+    }
+
+    /// matches an aliased element once, a type binds the token and a rule its node
+    fn check_element<'p> (
+        &'p self,
+        source: &mut TokenIterator,
+        elem: &'p Token<'u>,
+        alias: &'p Alias<'u, 'u>,
+        tokens: &mut Vec<Alias<'p, 'p>>,
+        children: &mut Vec<Child<'p>>,
+        memo: &mut Memo<'p>,
+    ) -> bool {
+
+        let name = elem.lexeme.unwrap();
+
+        if name.is_uppercase() {
+            if !source.check(&[Type(Type::from_str(name).unwrap())]) {
+                return false
+            }
+
+            tokens.push(Alias::new(alias.name, source.current));
+            source.next();
+
+        } else {
+            let rule = self.template.find_rule(name).unwrap();
+
+            match self.check_rule(source, rule, memo) {
+                Some(node) => children.push(Child { alias: alias.name, node }),
+                None       => return false,
+            }
+        }
+
+        true
     }
 }
+
+/// matches something as many times as a repetition allows, separated if it asks to be
+fn check_repeat<F> (source: &mut TokenIterator, repeat: &Repeat, mut check: F) -> bool
+    where F: FnMut(&mut TokenIterator) -> bool {
+
+    let mut count = 0;
+
+    while repeat.max.is_none_or(|max| count < max) {
+        let before = source.current;
+
+        if count > 0 {
+            if let Some(separator) = repeat.separator {
+                if !check_literal(source, separator, false) {
+                    break
+                }
+            }
+        }
+
+        if !check(source) {
+            source.current = before;
+            break
+        }
+
+        count += 1;
+
+        // something matching nothing would repeat forever
+        if source.current == before {
+            break
+        }
+    }
+
+    count >= repeat.min
+}
+
+/// matches an unaliased element, a type by its type and anything else by its lexeme
+fn check_literal (source: &mut TokenIterator, elem: &Token, escaped: bool) -> bool {
+    let lexeme = elem.lexeme.unwrap();
+
+    let matched = if !escaped && elem.token_type == Word && lexeme.is_uppercase() {
+        source.check(&[Type(Type::from_str(lexeme).unwrap())])
+    } else {
+        source.check(&[Lexeme(lexeme)])
+    };
+
+    if matched {
+        source.next();
+    }
+
+    matched
+}
@@ -0,0 +1,51 @@
+use super::token::Token;
+use super::error::Position;
+
+/// pairs of brackets set with `!/def/brackets`, as in `!/def/brackets ( ) [ ] { }`,
+/// lines are joined while one of them is open
+#[derive(Debug, Default)]
+pub struct Brackets<'d> {
+    pairs: Vec<(&'d str, &'d str)>,
+
+    // closing brackets expected, innermost last, along with where they were opened
+    open: Vec<(&'d str, Position)>,
+}
+
+impl<'d> Brackets<'d> {
+    /// reads opening and closing brackets two at a time, a last one on its own is an error
+    pub fn from_str (s: &'d str) -> Result<Self, (&'static str, &'d str)> {
+        let delims: Vec<&str> = s.split_whitespace().collect();
+        let mut brackets = Self::default();
+
+        for pair in delims.chunks(2) {
+            match *pair {
+                [open, close] => brackets.pairs.push((open, close)),
+                _ => return Err(("unpaired bracket", pair[0])),
+            }
+        }
+
+        Ok(brackets)
+    }
+
+    /// opens or closes a bracket if the symbol is one, a closing bracket that
+    /// doesn't match the innermost open one is left for the parser to report
+    pub fn track (&mut self, symbol: &Token) {
+        let lexeme = symbol.lexeme.unwrap_or("");
+
+        if let Some(&(_, close)) = self.pairs.iter().find(|pair| pair.0 == lexeme) {
+            self.open.push((close, symbol.position()));
+
+        } else if self.open.last().is_some_and(|&(close, _)| close == lexeme) {
+            self.open.pop();
+        }
+    }
+
+    /// where the brackets still open were opened, outermost first
+    pub fn unclosed (&self) -> impl Iterator<Item = Position> + '_ {
+        self.open.iter().map(|(_, position)| position.clone())
+    }
+
+    pub fn is_open (&self) -> bool {
+        !self.open.is_empty()
+    }
+}
@@ -0,0 +1,64 @@
+use std::cmp::Reverse;
+
+/// comments set with `!/def/line-comment`, as in `!/def/line-comment // #`,
+/// and `!/def/block-comment`, as in `!/def/block-comment /* */ nested`
+#[derive(Debug, Default)]
+pub struct CommentFormat<'d> {
+    pub line:  Vec<&'d str>,
+    pub block: Option<(&'d str, &'d str)>,
+
+    // whether a block comment can hold another
+    pub nested: bool,
+}
+
+impl<'d> CommentFormat<'d> {
+    /// reads the markers of both directives, or what is wrong with the block comment one
+    pub fn from_str (line: &'d str, block: &'d str) -> Result<Self, (&'static str, &'d str)> {
+        let mut format = Self { line: line.split_whitespace().collect(), ..Self::default() };
+
+        // longest first so `///` is tried before `//`
+        format.line.sort_by_key(|marker| Reverse(marker.len()));
+
+        let mut options = block.split_whitespace();
+
+        match (options.next(), options.next()) {
+            (Some(open), Some(close)) => format.block = Some((open, close)),
+            (Some(open), None)        => return Err(("block comment without a closing delimiter", open)),
+            _ => (),
+        }
+
+        for option in options {
+            match option {
+                "nested" => format.nested = true,
+                _        => return Err(("unknown block-comment option", option)),
+            }
+        }
+
+        Ok(format)
+    }
+
+    /// the line comment marker the text starts with
+    pub fn line_marker (&self, text: &str) -> Option<&'d str> {
+        self.line.iter().find(|marker| text.starts_with(**marker)).cloned()
+    }
+
+    pub fn opens (&self, text: &str) -> Option<&'d str> {
+        self.block.map(|block| block.0).filter(|open| text.starts_with(open))
+    }
+
+    pub fn closes (&self, text: &str) -> Option<&'d str> {
+        self.block.map(|block| block.1).filter(|close| text.starts_with(close))
+    }
+}
+
+/// a comment kept aside from the tokens so output can carry it over, leading the
+/// token it comes before, or trailing the one before it on the same line
+#[derive(Debug)]
+pub struct Comment {
+    pub line:     usize,
+    pub end_line: usize,
+    pub slice:    (usize, usize),
+
+    pub token:    usize,
+    pub trailing: bool,
+}
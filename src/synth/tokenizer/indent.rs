@@ -0,0 +1,136 @@
+use super::token::Token;
+use super::error::*;
+
+/// which characters indent lines and how wide they are, set with `!/def/indent`,
+/// as in `!/def/indent spaces`, `!/def/indent tabs` or `!/def/indent tab-width=4`
+#[derive(Debug)]
+pub struct IndentFormat {
+    pub spaces: bool,
+    pub tabs: bool,
+
+    // tabs and spaces can only be mixed when tabs have a width
+    pub tab_width: Option<usize>,
+}
+
+impl Default for IndentFormat {
+    fn default () -> Self {
+        Self { spaces: true, tabs: true, tab_width: None }
+    }
+}
+
+impl IndentFormat {
+    /// reads which of tabs and spaces are allowed and how wide a tab is
+    pub fn from_str (s: &str) -> Result<Self, (&'static str, &str)> {
+        let mut format = Self::default();
+
+        for option in s.split_whitespace() {
+            match option {
+                "spaces" => format.tabs = false,
+                "tabs"   => format.spaces = false,
+
+                _ if option.starts_with("tab-width=") => match option["tab-width=".len()..].parse() {
+                    Ok(width) if width > 0 => format.tab_width = Some(width),
+                    _                      => return Err(("unknown indent option", option)),
+                },
+
+                _ => return Err(("unknown indent option", option)),
+            }
+        }
+
+        Ok(format)
+    }
+
+    /// measures the indentation a line starts with, `style` being the character
+    /// lines were indented with so far, or describes what is wrong with it along
+    /// with the column it goes wrong at
+    pub fn measure (&self, indentation: &str, style: &mut Option<char>) -> Result<usize, (&'static str, usize)> {
+        let mut width = 0;
+
+        for (column, ch) in indentation.char_indices() {
+            let ch = if ch == '\t' { ch } else { ' ' };
+
+            if ch == '\t' && !self.tabs {
+                return Err(("tab in indentation, expected spaces", column))
+
+            } else if ch == ' ' && !self.spaces {
+                return Err(("space in indentation, expected tabs", column))
+            }
+
+            match self.tab_width {
+                Some(tab) if ch == '\t' => width += tab - width % tab,
+                Some(_) => width += 1,
+
+                None => {
+                    if style.is_some_and(|style| style != ch) {
+                        return Err(("inconsistent use of tabs and spaces in indentation", column))
+                    }
+
+                    *style = Some(ch);
+                    width += 1;
+                },
+            }
+        }
+
+        Ok(width)
+    }
+}
+
+/// tracks the indentation levels open in a source, turning the indentation
+/// of each line into the `Indent` and `Dedent` tokens it opens with
+#[derive(Debug, Default)]
+pub struct Indentation {
+    format: IndentFormat,
+    levels: Vec<usize>,
+
+    // character lines were indented with so far
+    style: Option<char>,
+}
+
+impl Indentation {
+    pub fn new (format: IndentFormat) -> Self {
+        Self { format, levels: Vec::new(), style: None }
+    }
+
+    /// layout tokens for a line starting with the given indentation,
+    /// an indentation that can't be measured or lined up is reported
+    pub fn line<'s> (&mut self, line: usize, indentation: &str, response: &mut Vec<Response<'s>>) -> Vec<Token<'s>> {
+        let mut tokens = Vec::new();
+
+        let width = match self.format.measure(indentation, &mut self.style) {
+            Ok(width) => width,
+            Err((message, column)) => {
+                response.push(Response::Error(message.into(), Position::new((line, column), 1)));
+                return tokens
+            },
+        };
+
+        if width > self.top() {
+            self.levels.push(width);
+            tokens.push(Token::indent(line));
+            return tokens
+        }
+
+        while width < self.top() {
+            self.levels.pop();
+            tokens.push(Token::dedent(line));
+        }
+
+        if width != self.top() {
+            response.push(Response::Error(
+                "unindent does not match any outer indentation level".into(),
+                Position::new((line, 0), indentation.len()),
+            ));
+        }
+
+        tokens
+    }
+
+    /// dedents closing every level still open at the end of a source
+    pub fn close<'s> (&mut self, line: usize) -> Vec<Token<'s>> {
+        self.levels.drain(..).map(|_| Token::dedent(line)).collect()
+    }
+
+    fn top (&self) -> usize {
+        *self.levels.last().unwrap_or(&0)
+    }
+}
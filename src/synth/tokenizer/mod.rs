@@ -1,4 +1,11 @@
+#[allow(clippy::module_inception)]
 pub mod tokenizer;
 pub mod token;
+pub mod number;
+pub mod word;
+pub mod string;
+pub mod indent;
+pub mod bracket;
+pub mod comment;
 
 pub use super::error;
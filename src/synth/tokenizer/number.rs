@@ -0,0 +1,226 @@
+/// which numeric literals the tokenizer reads as a single number,
+/// set with `!/def/number`, as in `!/def/number decimal exponent hex separator=_ suffix`
+#[derive(Debug, Default)]
+pub struct NumberFormat {
+    pub decimal:   bool,
+    pub exponent:  bool,
+    pub hex:       bool,
+    pub octal:     bool,
+    pub binary:    bool,
+    pub separator: Option<char>,
+    pub suffix:    bool,
+}
+
+impl NumberFormat {
+    /// reads the radixes, separator and suffix numbers may have,
+    /// any other option is an error naming it
+    pub fn from_str (s: &str) -> Result<Self, (&'static str, &str)> {
+        let mut format = Self::default();
+
+        for option in s.split_whitespace() {
+            match option {
+                "decimal"   => format.decimal = true,
+                "exponent"  => format.exponent = true,
+                "hex"       => format.hex = true,
+                "octal"     => format.octal = true,
+                "binary"    => format.binary = true,
+                "separator" => format.separator = Some('_'),
+                "suffix"    => format.suffix = true,
+
+                _ if option.starts_with("separator=") && option.chars().count() == 11 => {
+                    format.separator = option.chars().last()
+                },
+
+                _ => return Err(("unknown number option", option)),
+            }
+        }
+
+        Ok(format)
+    }
+
+    /// the length in bytes of the number starting the text
+    pub fn scan (&self, text: &str) -> usize {
+        let chars: Vec<char> = text.chars().collect();
+        let len = self.count(&chars);
+
+        chars[..len].iter().map(|c| c.len_utf8()).sum()
+    }
+
+    /// the number of characters of the number starting the text
+    fn count (&self, chars: &[char]) -> usize {
+
+        let radix = match chars.get(..2) {
+            Some(['0', 'x']) | Some(['0', 'X']) if self.hex => 16,
+            Some(['0', 'o']) | Some(['0', 'O']) if self.octal => 8,
+            Some(['0', 'b']) | Some(['0', 'B']) if self.binary => 2,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            let len = self.digits(&chars[2..], radix);
+
+            if len > 0 {
+                return self.suffix(chars, 2 + len)
+            }
+        }
+
+        let mut len = self.digits(chars, 10);
+
+        if self.decimal && chars.get(len) == Some(&'.') {
+            let fraction = self.digits(&chars[len + 1..], 10);
+
+            if fraction > 0 {
+                len += 1 + fraction;
+            }
+        }
+
+        if self.exponent && (chars.get(len) == Some(&'e') || chars.get(len) == Some(&'E')) {
+            let mut start = len + 1;
+
+            if chars.get(start) == Some(&'+') || chars.get(start) == Some(&'-') {
+                start += 1;
+            }
+
+            let exponent = self.digits(chars.get(start..).unwrap_or(&[]), 10);
+
+            if exponent > 0 {
+                len = start + exponent;
+            }
+        }
+
+        self.suffix(chars, len)
+    }
+
+    /// length of a run of digits, separators only count between two digits
+    fn digits (&self, chars: &[char], radix: u32) -> usize {
+        let mut len = 0;
+
+        while len < chars.len() {
+            let separated = len > 0 && Some(chars[len]) == self.separator
+                && chars.get(len + 1).is_some_and(|c| c.is_digit(radix));
+
+            if !chars[len].is_digit(radix) && !separated {
+                break
+            }
+
+            len += 1;
+        }
+
+        len
+    }
+
+    /// extends a number over the letters and digits of its suffix, as in `10u32`
+    fn suffix (&self, chars: &[char], mut len: usize) -> usize {
+        if self.suffix && chars.get(len).is_some_and(|c| c.is_alphabetic()) {
+            while len < chars.len() && (chars[len].is_alphanumeric() || chars[len] == '_') {
+                len += 1;
+            }
+        }
+
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NumberFormat;
+
+    fn format (options: &str) -> NumberFormat {
+        NumberFormat::from_str(options).unwrap()
+    }
+
+    #[test]
+    fn integers () {
+        let plain = format("");
+
+        assert_eq!(plain.scan("123"), 3);
+        assert_eq!(plain.scan("12 3"), 2);
+        assert_eq!(plain.scan("1.5"), 1);
+        assert_eq!(plain.scan("1e5"), 1);
+        assert_eq!(plain.scan("0x1f"), 1);
+        assert_eq!(plain.scan("1_0"), 1);
+        assert_eq!(plain.scan("10u8"), 2);
+    }
+
+    #[test]
+    fn decimal () {
+        let decimal = format("decimal");
+
+        assert_eq!(decimal.scan("1.5"), 3);
+        assert_eq!(decimal.scan("1."), 1);
+        assert_eq!(decimal.scan("1.x"), 1);
+        assert_eq!(decimal.scan("1.2.3"), 3);
+    }
+
+    #[test]
+    fn exponent () {
+        let exponent = format("decimal exponent");
+
+        assert_eq!(exponent.scan("1e5"), 3);
+        assert_eq!(exponent.scan("1E5"), 3);
+        assert_eq!(exponent.scan("1.5e-3"), 6);
+        assert_eq!(exponent.scan("1e+10"), 5);
+        assert_eq!(exponent.scan("1e"), 1);
+        assert_eq!(exponent.scan("1e+"), 1);
+        assert_eq!(exponent.scan("1ex"), 1);
+    }
+
+    #[test]
+    fn radix () {
+        let radix = format("hex octal binary");
+
+        assert_eq!(radix.scan("0x1f"), 4);
+        assert_eq!(radix.scan("0XFF"), 4);
+        assert_eq!(radix.scan("0x"), 1);
+        assert_eq!(radix.scan("0xg"), 1);
+        assert_eq!(radix.scan("0o17"), 4);
+        assert_eq!(radix.scan("0o8"), 1);
+        assert_eq!(radix.scan("0b101"), 5);
+        assert_eq!(radix.scan("0b2"), 1);
+
+        // prefixes are only read when their option is set
+        assert_eq!(format("octal").scan("0x1f"), 1);
+    }
+
+    #[test]
+    fn separator () {
+        let separator = format("separator hex");
+
+        assert_eq!(separator.scan("1_000"), 5);
+        assert_eq!(separator.scan("1__0"), 1);
+        assert_eq!(separator.scan("1_"), 1);
+        assert_eq!(separator.scan("_1"), 0);
+        assert_eq!(separator.scan("0xff_ff"), 7);
+
+        assert_eq!(format("separator='").scan("1'000"), 5);
+        assert_eq!(format("separator='").scan("1_000"), 1);
+    }
+
+    #[test]
+    fn suffix () {
+        let suffix = format("suffix decimal hex");
+
+        assert_eq!(suffix.scan("10u32"), 5);
+        assert_eq!(suffix.scan("1.5f"), 4);
+        assert_eq!(suffix.scan("0x1fu8"), 6);
+        assert_eq!(suffix.scan("10_u8"), 2);
+        assert_eq!(suffix.scan("10 u8"), 2);
+    }
+
+    #[test]
+    fn multibyte () {
+        assert_eq!(format("suffix").scan("1é"), 3);
+        assert_eq!(format("").scan("1é"), 1);
+    }
+
+    #[test]
+    fn options () {
+        assert!(NumberFormat::from_str("decimal exponent hex octal binary separator suffix").is_ok());
+        assert_eq!(format("separator").separator, Some('_'));
+        assert_eq!(format("separator=:").separator, Some(':'));
+
+        assert_eq!(NumberFormat::from_str("separator=").err(), Some(("unknown number option", "separator=")));
+        assert_eq!(NumberFormat::from_str("separator=ab").err(), Some(("unknown number option", "separator=ab")));
+        assert_eq!(NumberFormat::from_str("decimals").err(), Some(("unknown number option", "decimals")));
+    }
+}
@@ -0,0 +1,77 @@
+use std::cmp::Reverse;
+
+/// a kind of string literal, read between its delimiters
+#[derive(Debug)]
+pub struct StringKind<'d> {
+    pub open:      &'d str,
+    pub close:     &'d str,
+    pub escapes:   bool,
+    pub multiline: bool,
+}
+
+/// the kinds of string set by `!/def/string`, `!/def/multiline-string` and
+/// `!/def/raw-string`, the last taking pairs of delimiters as in `r" "`,
+/// longest opening delimiter first so `"""` is tried before `"`
+pub fn kinds<'d> (string: Option<&'d str>, multiline: Option<&'d str>, raw: Option<&'d str>) -> Vec<StringKind<'d>> {
+    let mut kinds = Vec::new();
+
+    for delim in string.unwrap_or("").split_whitespace() {
+        kinds.push(StringKind { open: delim, close: delim, escapes: true, multiline: false });
+    }
+
+    for delim in multiline.unwrap_or("").split_whitespace() {
+        kinds.push(StringKind { open: delim, close: delim, escapes: true, multiline: true });
+    }
+
+    let raw: Vec<&str> = raw.unwrap_or("").split_whitespace().collect();
+
+    // a delimiter left without a pair closes itself
+    for pair in raw.chunks(2) {
+        kinds.push(StringKind { open: pair[0], close: pair[pair.len() - 1], escapes: false, multiline: false });
+    }
+
+    kinds.sort_by_key(|kind| Reverse(kind.open.len()));
+    kinds
+}
+
+/// reads a string from a column up to its closing delimiter, adding the decoded
+/// text to `value`, returns the column after the delimiter if it closes on this line
+pub fn scan (line: &str, from: usize, kind: &StringKind, value: &mut String) -> Option<usize> {
+    let rest = &line[from..];
+    let mut iter = rest.char_indices();
+
+    while let Some((index, ch)) = iter.next() {
+        if rest[index..].starts_with(kind.close) {
+            return Some(from + index + kind.close.len())
+        }
+
+        if ch == '\\' && kind.escapes {
+            match iter.next() {
+                Some((_, escaped)) => unescape(escaped, value),
+                None               => value.push(ch),
+            }
+
+        } else {
+            value.push(ch);
+        }
+    }
+
+    None
+}
+
+/// decodes the character following a `\`, unknown escapes are kept as written
+fn unescape (escaped: char, value: &mut String) {
+    match escaped {
+        'n'  => value.push('\n'),
+        't'  => value.push('\t'),
+        'r'  => value.push('\r'),
+        '0'  => value.push('\0'),
+
+        '\\' | '"' | '\'' => value.push(escaped),
+
+        _ => {
+            value.push('\\');
+            value.push(escaped);
+        },
+    }
+}
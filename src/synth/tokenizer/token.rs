@@ -1,4 +1,7 @@
+use super::error::Position;
+
 #[derive(Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Type {
     Number,
     String,
@@ -59,6 +62,12 @@ pub struct Token<'t> {
     pub line:       usize,
     pub slice:      (usize, usize),
     pub lexeme:     Option<&'t str>,
+
+    // decoded text of a string, and the line a multi-line string ends on,
+    // its lexeme only being what's on the first line after the opening delimiter
+    pub value:      Option<String>,
+    pub end_line:   usize,
+    pub open:       usize,
 }
 
 impl<'t> PartialEq<PartialToken<'t>> for Token<'t> {
@@ -66,10 +75,10 @@ impl<'t> PartialEq<PartialToken<'t>> for Token<'t> {
         match *rhs {
             PartialToken::Type(ref t)   =>
                 self.token_type == *t,
-            PartialToken::Lexeme(ref l) =>
-                self.lexeme == Some(*l),
-            PartialToken::Pair(ref t, ref l) => {
-                self.lexeme == Some(*l) && self.token_type == *t
+            PartialToken::Lexeme(l) =>
+                self.lexeme == Some(l),
+            PartialToken::Pair(ref t, l) => {
+                self.lexeme == Some(l) && self.token_type == *t
             },
         }
     }
@@ -83,23 +92,23 @@ impl<'t> PartialEq<Token<'t>> for PartialToken<'t> {
 
 
 impl<'t> Token<'t> {
-    pub fn new (token_type: Type, line: usize, slice: (usize, usize), lexeme: Option<&str>) -> Token {
-        Token { token_type, line, slice, lexeme }
+    pub fn new (token_type: Type, line: usize, slice: (usize, usize), lexeme: Option<&str>) -> Token<'_> {
+        Token { token_type, line, slice, lexeme, value: None, end_line: line, open: 0 }
     }
 
-    pub fn number(line: usize, slice: (usize, usize), lexeme: &str) -> Token {
+    pub fn number(line: usize, slice: (usize, usize), lexeme: &str) -> Token<'_> {
         Token::new(Type::Number, line, slice, Some(lexeme))
     }
 
-    pub fn string(line: usize, slice: (usize, usize), lexeme: &str) -> Token {
-        Token::new(Type::String, line, slice, Some(lexeme))
+    pub fn string(line: usize, slice: (usize, usize), lexeme: &str, value: String) -> Token<'_> {
+        Token { value: Some(value), ..Token::new(Type::String, line, slice, Some(lexeme)) }
     }
 
-    pub fn word(line: usize, slice: (usize, usize), lexeme: &str) -> Token {
+    pub fn word(line: usize, slice: (usize, usize), lexeme: &str) -> Token<'_> {
         Token::new(Type::Word, line, slice, Some(lexeme))
     }
 
-    pub fn symbol(line: usize, slice: (usize, usize), lexeme: &str) -> Token {
+    pub fn symbol(line: usize, slice: (usize, usize), lexeme: &str) -> Token<'_> {
         Token::new(Type::Symbol, line, slice, Some(lexeme))
     }
 
@@ -111,13 +120,22 @@ impl<'t> Token<'t> {
         Token::new(Type::Dedent, line, (0,0), None)
     }
 
-    pub fn newline<'b>(line: usize) -> Token<'b> {
-        Token::new(Type::EOL, line, (0,0), None)
+    pub fn newline<'b>(line: usize, column: usize) -> Token<'b> {
+        Token::new(Type::EOL, line, (column, column + 1), None)
     }
 
     pub fn eof<'b>(line: usize) -> Token<'b> {
         Token::new(Type::EOF, line, (0,0), None)
     }
+
+    /// where the token is, a token over several lines is marked on its first
+    pub fn position(&self) -> Position {
+        if self.end_line > self.line {
+            Position::new((self.line, self.slice.0), self.open + self.lexeme.map_or(0, str::len))
+        } else {
+            Position::new((self.line, self.slice.0), self.slice.1 - self.slice.0)
+        }
+    }
 }
 
 
@@ -129,7 +147,7 @@ pub struct TokenIterator<'t, 's: 't> {
 
 impl<'t, 's: 't> TokenIterator<'t, 's> {
     pub fn new (tokens: &'t Vec<Token<'s>>) -> Self {
-        Self { tokens: tokens, current: 0 }
+        Self { tokens, current: 0 }
     }
 
     pub fn get (&self, offset: usize) -> Option<&'t Token<'s>> {
@@ -162,14 +180,10 @@ impl<'t, 's: 't> TokenIterator<'t, 's> {
             return false
         }
 
-        let mut offset = 0;
-
-        for token in tokens {
+        for (offset, token) in tokens.iter().enumerate() {
             if token != self.get(offset).unwrap() {
                 return false
             }
-
-            offset += 1;
         }
 
         true
@@ -184,4 +198,40 @@ impl<'t, 's: 't> TokenIterator<'t, 's> {
             false
         }
     }
+
+    /// skips past the end of the current line, along with any block indented under it
+    pub fn skip_line (&mut self) {
+        if self.match_with(&[PartialToken::Type(Type::Dedent)]) {
+            return
+        }
+
+        while !self.check(&[PartialToken::Type(Type::EOF)])
+            && !self.check(&[PartialToken::Type(Type::Indent)])
+            && !self.match_with(&[PartialToken::Type(Type::EOL)]) {
+            self.next();
+        }
+
+        self.skip_block();
+    }
+
+    /// skips an indented block, if one starts at the current token
+    pub fn skip_block (&mut self) {
+        if !self.match_with(&[PartialToken::Type(Type::Indent)]) {
+            return
+        }
+
+        let mut dent = 1;
+
+        while dent > 0 && !self.check(&[PartialToken::Type(Type::EOF)]) {
+            if self.match_with(&[PartialToken::Type(Type::Indent)]) {
+                dent += 1;
+
+            } else if self.match_with(&[PartialToken::Type(Type::Dedent)]) {
+                dent -= 1;
+
+            } else {
+                self.next();
+            }
+        }
+    }
 }
@@ -1,137 +1,279 @@
-use std::iter::{Peekable, Enumerate};
-use std::str::Chars;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use std::borrow::Cow;
+use std::cmp::Reverse;
 
 use super::token::{Type as T, Token, PartialToken};
+use super::number::NumberFormat;
+use super::word::WordFormat;
+use super::indent::{IndentFormat, Indentation};
+use super::bracket::Brackets;
+use super::comment::{CommentFormat, Comment};
+use super::string::{self, StringKind};
 use self::PartialToken::{Type};
 
 use super::error::*;
 
 #[derive(Debug)]
 pub struct Source<'s> {
+    // kept for reporting, units are currently reported by the caller
+    #[allow(dead_code)]
     pub path:       &'s str,
     pub lines:      Vec<&'s str>,
     pub tokens:     Option<Vec<Token<'s>>>,
-    pub directives: Vec<(&'s str, &'s str)>,
+    pub comments:   Vec<Comment>,
+    // name and value of each directive, with the line and column the value starts at
+    pub directives: Vec<(&'s str, &'s str, (usize, usize))>,
+
+    // whether the source is a template, read with its directives
+    pub template:   bool,
 }
 
 impl<'s> Source<'s> {
     pub fn new(path: &'s str, ctrl_char: Option<&str>, source_lines: &'s Vec<String>) -> Self {
 
         let mut lines: Vec<&str> = Vec::new();
-        let mut directives: Vec<(&str, &str)> = Vec::new();
+        let mut directives: Vec<(&str, &str, (usize, usize))> = Vec::new();
 
         if let Some(ctrl) = ctrl_char {
-            for line in source_lines {
-                if line.starts_with(ctrl) {
-                    directives.push ((
-                        &line[ctrl.len() .. line.find(" ").unwrap()],
-                        &line[line.find(" ").unwrap() + 1..],
-                    ));
+            for (l, line) in source_lines.iter().enumerate() {
+                if let Some(directive) = line.strip_prefix(ctrl) {
+                    // a directive without a value is read as an empty one, left for tokenizing to report
+                    let (name, value) = directive.split_once(' ').unwrap_or((directive, ""));
+
+                    directives.push ((name, value, (l + 1, line.len() - value.len())));
                     lines.push("");
 
                 } else {
-                    lines.push(&line);
+                    lines.push(line);
                 }
             }
 
         } else {
             for line in source_lines {
-                lines.push(&line)
+                lines.push(line)
             }
         }
 
         Self {
-            path:       path,
-            lines:      lines,
+            path,
+            lines,
             tokens:     None,
-            directives: directives,
+            comments:   Vec::new(),
+            directives,
+            template:   ctrl_char.is_some(),
+        }
+    }
+
+    pub fn get_directive (&self, name: &str) -> Option<&'s str> {
+        self.directives.iter().find(|n| n.0 == name).map(|n| n.1)
+    }
+
+    /// reads the value of a directive with the parser for its options, an option it
+    /// can't read is reported where it is written and the defaults are used instead
+    fn options<T, F> (&self, name: &str, parse: F, response: &mut Vec<Response<'s>>) -> T
+        where T: Default, F: FnOnce(&'s str) -> Result<T, (&'static str, &'s str)> {
+
+        match parse(self.get_directive(name).unwrap_or("")) {
+            Ok(options) => options,
+            Err((message, option)) => {
+                response.push(Response::Error(format!("{} {}", message, option).into(), self.directive_position(name, option)));
+                T::default()
+            },
+        }
+    }
+
+    /// position of an option in the value of a directive, for reporting it
+    pub fn directive_position (&self, name: &str, option: &str) -> Position {
+        match self.directives.iter().find(|n| n.0 == name) {
+            Some(&(_, value, (line, column))) => {
+                Position::new((line, column + value.find(option).unwrap_or(0)), option.len().max(1))
+            },
+            None => Position::new((1, 0), 0),
+        }
+    }
+
+    /// source text from the start of one token to the end of another on the same line,
+    /// string delimiters included
+    pub fn span (&self, from: &Token<'s>, to: &Token<'s>) -> &'s str {
+        let (start, end) = self.bounds(from, to);
+        &self.lines[from.line - 1][start..end]
+    }
+
+    /// columns the text returned by `span` starts and ends at
+    pub fn bounds (&self, from: &Token<'s>, to: &Token<'s>) -> (usize, usize) {
+        (from.slice.0, to.slice.1)
+    }
+
+    /// source text of a token, which for a multi-line string runs over several lines
+    pub fn text (&self, token: &Token<'s>) -> Cow<'s, str> {
+        self.extract(token.line, token.end_line, token.slice)
+    }
+
+    /// source text of a comment as written, which for a block comment runs over several lines
+    pub fn comment_text (&self, comment: &Comment) -> Cow<'s, str> {
+        self.extract(comment.line, comment.end_line, comment.slice)
+    }
+
+    fn extract (&self, line: usize, end_line: usize, slice: (usize, usize)) -> Cow<'s, str> {
+        if end_line == line {
+            return Cow::Borrowed(&self.lines[line - 1][slice.0..slice.1])
+        }
+
+        let mut text = self.lines[line - 1][slice.0..].to_string();
+
+        for line in &self.lines[line..end_line - 1] {
+            text.push('\n');
+            text.push_str(line);
         }
+
+        text.push('\n');
+        text.push_str(&self.lines[end_line - 1][..slice.1]);
+
+        Cow::Owned(text)
     }
 
-    pub fn get_directive (&self, name: &str) -> Option<&str> {
-        match self.directives.iter().find(|n| n.0 == name).map(|n| &n.1) {
-            Some(n) => Some(n.to_owned()),
-            None    => None,
+    pub fn dump (&self) {
+        for token in self.tokens.as_ref().unwrap().iter() {
+            println!("{}:{}\t{}\t{}",
+                token.line, token.slice.0,
+                token.token_type.to_str(),
+                token.lexeme.unwrap_or("")
+            );
         }
     }
 
-    pub fn tokenize(&mut self) -> CompileResult<(), ()> {
+    pub fn tokenize(&mut self) -> CompileResult<'_, (), ()> {
         let mut response = Vec::new();
         
-        let mut indents = Vec::new();
         let mut tokens  = Vec::new();
 
-        let mut comment = 0; // start of block comment
-        let mut flag = false;
+        // where each block comment still open starts, innermost last,
+        // and the comments read so far
+        let mut blocks: Vec<(usize, usize)> = Vec::new();
+        let mut comments = Vec::new();
 
-        for (mut l, line) in self.lines.iter().enumerate() {
-            l += 1; // line offset
+        // directives for comments
+        let comment = self.options("block-comment", |block| {
+            CommentFormat::from_str(self.get_directive("line-comment").unwrap_or(""), block)
+        }, &mut response);
 
-            let mut indent = 0;
-            let mut start = false; // start of content (after indent)
-            let mut iter = line.chars().enumerate().peekable();
+        // directives which mean nothing without a value
+        for name in ["line-comment", "block-comment", "string", "multiline-string", "raw-string", "symbols", "brackets", "continuation", "extension"] {
+            if self.get_directive(name).is_some_and(|value| value.trim().is_empty()) {
+                response.push(Response::Error(format!("the {} directive needs a value", name).into(), self.directive_position(name, "")));
+            }
+        }
 
-            let mut string_d:  Vec<&str> = Vec::new();
-            let mut comment_d: Vec<&str> = Vec::new();
+        if let Some(value) = self.get_directive("comment") {
+            response.push(Response::Error(
+                "the comment directive is replaced by line-comment and block-comment".into(),
+                self.directive_position("comment", value),
+            ));
+        }
 
-            // directive for string parsing
-            if let Some(string_delim) = self.get_directive("string") {
-                for delim in string_delim.split_whitespace() {
-                    string_d.push(delim);
-                }
-            }
+        // directives for numeric literals, identifier characters and indentation
+        let number = self.options("number", NumberFormat::from_str, &mut response);
+        let word = self.options("word", WordFormat::from_str, &mut response);
+        let mut indents = Indentation::new(self.options("indent", IndentFormat::from_str, &mut response));
 
-            // directive for comment parsing
-            if let Some(comment_delim) = self.get_directive("comment") {
-                for delim in comment_delim.split_whitespace() {
-                    comment_d.push(delim);
-                }
+        // directive for multi-character symbols, longest first so matching is greedy
+        let mut symbols: Vec<&str> = self.get_directive("symbols").unwrap_or("").split_whitespace().collect();
+        symbols.sort_by_key(|symbol| Reverse(symbol.chars().count()));
 
-                if comment_d.len() > 3 && !flag {
-                    response.push(Response::Error("too many comment delimiters", Position::new((l, l), 0)));
-                    flag = true
+        // directives for line joining, within brackets or after a continuation character,
+        // which only apply to units as output in a template holds brackets of its own
+        let mut brackets = self.options("brackets", Brackets::from_str, &mut response);
+
+        let continuation = self.get_directive("continuation").map(str::trim).filter(|_| !self.template);
+        let mut joined = false;
+
+        // directives for string parsing
+        let strings = string::kinds(
+            self.get_directive("string"),
+            self.get_directive("multiline-string"),
+            self.get_directive("raw-string"),
+        );
+
+        // multi-line string still open, with where it started and what it holds so far
+        let mut open: Option<(&StringKind, usize, usize, &str, String)> = None;
+
+        for (mut l, line) in self.lines.iter().enumerate() {
+            l += 1; // line offset
+
+            let mut start = false; // start of content (after indent)
+            let mut content = 0;
+            let mut iter = line.char_indices().peekable();
+
+            // a line starting inside a comment or string carries on the one before
+            let continued = !blocks.is_empty() || open.is_some() || brackets.is_open() || joined;
+            let first = tokens.len();
+            joined = false;
+
+            if let Some((kind, first, column, lexeme, mut value)) = open.take() {
+                match string::scan(line, 0, kind, &mut value) {
+                    Some(to) => {
+                        let mut token = Token::string(first, (column, to), lexeme, value);
+                        token.end_line = l;
+                        token.open = kind.open.len();
+
+                        tokens.push(token);
+                        skip(&mut iter, to);
+                        start = true;
+                    },
+
+                    None => {
+                        value.push('\n');
+                        open = Some((kind, first, column, lexeme, value));
+                        continue
+                    },
                 }
             }
 
             while let Some((from, next)) = iter.next() {
                 if !start && next.is_whitespace() {
-                    indent += 1;
+                    continue
 
                 } else if !start {
                     start = true;
-
-                    if indent < *indents.last().unwrap_or(&0) {
-                        while indent < *indents.last().unwrap_or(&0) {
-                            if comment == 0 {
-                                tokens.push(Token::dedent(l));
-                                indents.pop();
-                            }
-                        }
-
-                    } else if indent > *indents.last().unwrap_or(&0) {
-                        if comment == 0 {
-                            indents.push(indent);
-                            tokens.push(Token::indent(l));
-                        }
-                    }
+                    content = from;
                 }
 
                 if start {
-                    if comment == 0 {
-                        if next.is_numeric() {
-                            while let Some(&(_, next)) = iter.peek() {
-                                if !next.is_numeric() {
+                    if blocks.is_empty() {
+                        if let Some(kind) = strings.iter().find(|kind| line[from..].starts_with(kind.open)) {
+                            let content = from + kind.open.len();
+                            let mut value = String::new();
+
+                            match string::scan(line, content, kind, &mut value) {
+                                Some(to) => {
+                                    tokens.push(Token::string(l, (from, to), &line[content..to - kind.close.len()], value));
+                                    skip(&mut iter, to);
+                                },
+
+                                None if kind.multiline => {
+                                    value.push('\n');
+                                    open = Some((kind, l, from, &line[content..], value));
                                     break
-                                }
-                                iter.next();
+                                },
+
+                                None => {
+                                    response.push(Response::Error(
+                                        "unterminated string".into(), Position::new((l, from), kind.open.len())
+                                    ));
+                                    break
+                                },
                             }
 
-                            let to = iter.peek().map(|v| v.0).unwrap_or(line.len());
-                            tokens.push(Token::number(l, (from, to), &line[from..to]))
+                        } else if next.is_ascii_digit() {
+                            let to = from + number.scan(&line[from..]);
 
-                        } else if next.is_alphabetic() {
+                            tokens.push(Token::number(l, (from, to), &line[from..to]));
+                            skip(&mut iter, to);
+
+                        } else if word.is_start(next) {
                             while let Some(&(_, next)) = iter.peek() {
-                                if !next.is_alphanumeric() {
+                                if !word.is_continue(next) {
                                     break
                                 }
                                 iter.next();
@@ -140,88 +282,116 @@ impl<'s> Source<'s> {
                             let to = iter.peek().map(|v| v.0).unwrap_or(line.len());
                             tokens.push(Token::word(l, (from, to), &line[from..to]))
 
-                        } else if let Some(delim) = self.matches(next, &mut iter.clone(), &string_d) {
-                            let mut last = next;
-
-                            while let Some(&(to, next)) = iter.peek() {
-                                if last != '\\'
-                                && self.matches(next, &mut iter.clone(), &vec![delim.clone()]) != None {
-                                    tokens.push(Token::string(l, (from+1, to), &line[from+1..to]));
-                                    iter.nth(delim.len()-1);
-                                    break
-                                }
-
-                                last = next;
-                                iter.next();
-                            }
+                        } else if let Some(open) = comment.opens(&line[from..]) {
+                            blocks.push((l, from));
+                            skip(&mut iter, from + open.len());
 
-                        } else if let Some(delim) = self.matches(next, &mut iter.clone(), &comment_d) {
-                            iter.nth(delim.len()-1);
-                            
-                            match comment_d.len() {
-                                1 => { // single line
-                                    break // skip the rest of the line
-                                },
+                        } else if comment.line_marker(&line[from..]).is_some() {
+                            let trailing = tokens.len() > first;
 
-                                2 => { // block
-                                    if comment_d[1] == delim {
-                                        response.push(Response::Error("unexpected block comment terminator", Position::new((from, from + 2), 2)));
-                                    } else {
-                                        comment = l // block comment
-                                    }
-                                },
+                            comments.push(Comment {
+                                line: l, end_line: l, slice: (from, line.len()),
+                                token: tokens.len() - trailing as usize, trailing,
+                            });
+                            break // skip the rest of the line
 
-                                3 => { // block and single line
-                                    if comment_d[1] == delim {
-                                        response.push(Response::Error("unexpected block comment terminator", Position::new((from, from + 2), 2)));
-                                    } else if comment_d[0] == delim {
-                                        comment = l; // block comment
-                                        break
+                        } else if let Some(close) = comment.closes(&line[from..]) {
+                            response.push(Response::Error(
+                                "unexpected block comment terminator".into(), Position::new((l, from), close.len())
+                            ));
+                            skip(&mut iter, from + close.len());
 
-                                    } else {
-                                        break // single line
-                                    }
-                                },
+                        } else if let Some(symbol) = self.matches(next, &mut iter.clone(), &symbols) {
+                            let to = from + symbol.len();
 
-                                _ => (),
-                            }
+                            tokens.push(Token::symbol(l, (from, to), &line[from..to]));
+                            skip(&mut iter, to);
 
                         } else if !next.is_whitespace()  {
-                            tokens.push(Token::symbol(l, (from, from+1), &line[from..from+1]));
+                            let to = from + next.len_utf8();
+                            tokens.push(Token::symbol(l, (from, to), &line[from..to]));
                         }
 
-                    } else {
-                        if let Some(delim) = self.matches(next, &mut iter.clone(), &comment_d) {
-                            iter.nth(delim.len()-1);
+                    } else if let Some(close) = comment.closes(&line[from..]) {
+                        let (start, column) = blocks.pop().unwrap();
+                        skip(&mut iter, from + close.len());
 
-                            match comment_d.len() {
-                                2 | 3 => if comment_d[1] == delim { comment = 0 },
-                                _ => (),
-                            }
+                        // a block comment is trailing when code comes before it on the line it starts on
+                        if blocks.is_empty() {
+                            let trailing = start == l && tokens.len() > first;
+
+                            comments.push(Comment {
+                                line: start, end_line: l, slice: (column, from + close.len()),
+                                token: tokens.len() - trailing as usize, trailing,
+                            });
                         }
+
+                    } else if let Some(open) = comment.opens(&line[from..]).filter(|_| comment.nested) {
+                        blocks.push((l, from));
+                        skip(&mut iter, from + open.len());
+                    }
+                }
+            }
+
+            if !self.template {
+                for token in &tokens[first..] {
+                    if token.token_type == T::Symbol {
+                        brackets.track(token);
                     }
                 }
             }
 
-            if comment == 0 && tokens.last().map(
+            // a continuation character ending a line joins it with the next
+            if continuation.is_some() && tokens[first..].last().is_some_and(|t| t.token_type == T::Symbol && t.lexeme == continuation) {
+                tokens.pop();
+                joined = true;
+            }
+
+            // indentation only counts on lines with tokens, comments alone leave it as is
+            if !continued && tokens.len() > first {
+                let layout = indents.line(l, &line[..content], &mut response);
+
+                for comment in comments.iter_mut().filter(|comment| comment.token >= first) {
+                    comment.token += layout.len();
+                }
+
+                tokens.splice(first..first, layout);
+            }
+
+            if blocks.is_empty() && open.is_none() && !brackets.is_open() && !joined && tokens.last().map(
                 |t| t != &Type(T::EOL) && t != &Type(T::Dedent)
             ).unwrap_or(false) {
-                tokens.push(Token::newline(l))
+                tokens.push(Token::newline(l, line.len()))
             }
         }
 
-        if comment != 0 {
-            response.push(Response::Error("unterminated block comment", Position::new((comment, 0), 2)));
+        if let Some((kind, first, column, _, _)) = open {
+            response.push(Response::Error("unterminated string".into(), Position::new((first, column), kind.open.len())));
+        }
+
+        for position in brackets.unclosed() {
+            response.push(Response::Error("unclosed bracket".into(), position));
         }
 
-        for _ in indents {
-            tokens.push(Token::dedent(self.lines.len()))
+        if let (Some(&start), Some((open, _))) = (blocks.first(), comment.block) {
+            response.push(Response::Error("unterminated block comment".into(), Position::new(start, open.len())));
         }
 
+        tokens.extend(indents.close(self.lines.len()));
+
         tokens.push(Token::eof(self.lines.len()));
+
+        // leading comments belong to the token after any indent or dedent before it
+        for comment in comments.iter_mut().filter(|comment| !comment.trailing) {
+            while tokens[comment.token] == Type(T::Indent) || tokens[comment.token] == Type(T::Dedent) {
+                comment.token += 1;
+            }
+        }
+
         self.tokens = Some(tokens);
+        self.comments = comments;
         
-        if response.len() > 0 {
+        if !response.is_empty() {
             Err(Outcome::new((), Some(response)))
         } else {
             Ok(())
@@ -229,7 +399,7 @@ impl<'s> Source<'s> {
     }
 
     fn matches (
-        &self, first: char, iter: &mut Peekable<Enumerate<Chars>>, delims: &Vec<&'s str>
+        &self, first: char, iter: &mut Peekable<CharIndices>, delims: &Vec<&'s str>
     ) -> Option<&'s str> {
 
         let mut matched = true;
@@ -252,7 +422,7 @@ impl<'s> Source<'s> {
             }
 
             if matched {
-                return Some((*delim).clone())
+                return Some(*delim)
 
             } else {
                 matched = true
@@ -262,3 +432,10 @@ impl<'s> Source<'s> {
         None
     }
 }
+
+/// moves the iterator on to a column, past what a token has taken
+fn skip (iter: &mut Peekable<CharIndices>, to: usize) {
+    while iter.peek().is_some_and(|&(index, _)| index < to) {
+        iter.next();
+    }
+}
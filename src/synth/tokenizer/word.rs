@@ -0,0 +1,37 @@
+/// which characters words start and continue with besides letters and digits,
+/// set with `!/def/word`, as in `!/def/word start=_ continue=_?`
+#[derive(Debug, Default)]
+pub struct WordFormat {
+    pub start: Vec<char>,
+    pub rest:  Vec<char>,
+}
+
+impl WordFormat {
+    /// reads the extra characters given by `start=` and `continue=`
+    pub fn from_str (s: &str) -> Result<Self, (&'static str, &str)> {
+        let mut format = Self::default();
+
+        for option in s.split_whitespace() {
+            if let Some(chars) = option.strip_prefix("start=") {
+                format.start.extend(chars.chars());
+
+            } else if let Some(chars) = option.strip_prefix("continue=") {
+                format.rest.extend(chars.chars());
+
+            } else {
+                return Err(("unknown word option", option))
+            }
+        }
+
+        Ok(format)
+    }
+
+    pub fn is_start (&self, c: char) -> bool {
+        c.is_alphabetic() || self.start.contains(&c)
+    }
+
+    /// whether a character continues a word, any it can start with included
+    pub fn is_continue (&self, c: char) -> bool {
+        c.is_alphanumeric() || self.start.contains(&c) || self.rest.contains(&c)
+    }
+}
@@ -0,0 +1,9 @@
+!/def/string "
+
+statement:
+    value := v (x:WORD | x:NUMBER) EOL
+        [output]
+            write: value {x};
+    pair := p (x:WORD y:WORD | x:NUMBER | y:STRING) EOL
+        [output]
+            write: pair {x} {y};
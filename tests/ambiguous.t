@@ -0,0 +1,9 @@
+p:
+    p := a:val b:val EOL
+        [output]
+            write: {val}
+
+val!
+    n := n:NUMBER
+        [output]
+            write: {n}
@@ -0,0 +1,22 @@
+!/def/string "
+
+statement:
+    hide := hide e:val EOL
+        [output]
+            write: hidden
+
+    let := let n:WORD = e:val EOL
+        [output]
+            write: typeof({e}) {n} = {e};
+
+val!
+    bad := bad:WORD
+        [output]
+            error: bad, "bare words are not values"
+            write: {bad}
+
+    string := s:STRING
+        [output]
+            warning: s, "strings are not translated"
+            note: s, "kept as written"
+            write: {s}
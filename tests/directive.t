@@ -0,0 +1,4 @@
+!/def/line-comment
+!/def/string 
+!/def/number
+
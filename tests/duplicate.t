@@ -0,0 +1,10 @@
+statement:
+    twice := t x:WORD x:NUMBER EOL
+        [output]
+            write: {x};
+    outside := o x:WORD (x:NUMBER | y:STRING) EOL
+        [output]
+            write: {x};
+    branch := b (x:WORD x:NUMBER | y:STRING) EOL
+        [output]
+            write: {y};
@@ -0,0 +1,9 @@
+s:
+    v := x:n
+        [output]
+            write: {x}
+
+n!
+    m := y:WORD*
+        [output]
+            write: {y, " "}
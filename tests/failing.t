@@ -1,4 +1,6 @@
-!/def/comment /* */ // abc
+!/def/line-comment // #
+!/def/block-comment /* */ nested
 !/def/string " '
 
 /*
+    /* a nested comment */
@@ -0,0 +1,276 @@
+// runs the templates and units in this directory through synth, comparing what
+// it writes with `<name>.out` for the generated code and `<name>.err` for the
+// diagnostics, a missing file meaning nothing is expected
+
+use std::fs;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// longer than any fixture takes, so a hang fails instead of stalling the run
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn synth (args: &[&str]) -> (Option<i32>, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_synth"))
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let start = Instant::now();
+
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status
+        }
+
+        if start.elapsed() > TIMEOUT {
+            child.kill().unwrap();
+            panic!("synth {} did not finish", args.join(" "))
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let mut out = String::new();
+    let mut err = String::new();
+
+    child.stdout.take().unwrap().read_to_string(&mut out).unwrap();
+    child.stderr.take().unwrap().read_to_string(&mut err).unwrap();
+
+    (status.code(), out, err)
+}
+
+fn expected (name: &str, extension: &str) -> String {
+    fs::read_to_string(format!("tests/{}.{}", name, extension)).unwrap_or_default()
+}
+
+// exits with 1 when an error is reported and 0 otherwise
+fn check (name: &str, args: &[&str]) {
+    let code = if expected(name, "err").contains("error") { 1 } else { 0 };
+    exits(name, args, code);
+}
+
+fn exits (name: &str, args: &[&str], code: i32) {
+    let (status, out, err) = synth(args);
+
+    assert_eq!(out, expected(name, "out"), "generated code of {}", name);
+    assert_eq!(err, expected(name, "err"), "diagnostics of {}", name);
+    assert_eq!(status, Some(code), "exit status of {}", name);
+}
+
+fn build (template: &str, unit: &str) {
+    let name = unit.trim_end_matches(".pi");
+    check(name, &["build", "-t", &format!("tests/{}", template), &format!("tests/{}", unit)]);
+}
+
+#[test]
+fn unit1 () {
+    build("test.t", "unit1.pi");
+}
+
+#[test]
+fn unit2 () {
+    build("test.t", "unit2.pi");
+}
+
+#[test]
+fn number () {
+    build("number.t", "number.pi");
+}
+
+#[test]
+fn indent () {
+    build("test.t", "indent.pi");
+}
+
+#[test]
+fn unindent () {
+    build("test.t", "unindent.pi");
+}
+
+#[test]
+fn join () {
+    build("test.t", "join.pi");
+}
+
+#[test]
+fn comment () {
+    build("test.t", "comment.pi");
+}
+
+#[test]
+fn failing () {
+    check("failing", &["check", "-t", "tests/failing.t"]);
+}
+
+#[test]
+fn empty () {
+    build("empty.t", "empty.pi");
+}
+
+#[test]
+fn blank () {
+    build("empty.t", "blank.pi");
+}
+
+#[test]
+fn nullable () {
+    check("nullable", &["check", "-t", "tests/nullable.t"]);
+}
+
+#[test]
+fn recover () {
+    build("recover.t", "recover.pi");
+}
+
+#[test]
+fn ambiguous () {
+    check("ambiguous", &["check", "-t", "tests/ambiguous.t"]);
+}
+
+#[test]
+fn multiline () {
+    build("multiline.t", "multiline.pi");
+}
+
+#[test]
+fn unclosed () {
+    build("test.t", "unclosed.pi");
+}
+
+#[test]
+fn directive () {
+    check("directive", &["check", "-t", "tests/directive.t"]);
+}
+
+#[test]
+fn hidden () {
+    build("diagnose.t", "hidden.pi");
+}
+
+// a child written twice is still only diagnosed once
+#[test]
+fn twice () {
+    build("diagnose.t", "twice.pi");
+
+    let (_, _, err) = synth(&["build", "-t", "tests/diagnose.t", "tests/twice.pi"]);
+    assert_eq!(err.matches("warning: strings are not translated").count(), 1);
+    assert_eq!(err.matches("note: kept as written").count(), 1);
+}
+
+#[test]
+fn usage () {
+    exits("usage", &["bogus"], 2);
+    exits("units", &["build", "-t", "tests/test.t", "--color=never"], 2);
+}
+
+#[test]
+fn quiet () {
+    check("quiet", &["build", "-q", "-t", "tests/diagnose.t", "tests/twice.pi"]);
+}
+
+#[test]
+fn output () {
+    let dir = format!("{}/output", env!("CARGO_TARGET_TMPDIR"));
+
+    exits("output", &["build", "-t", "tests/test.t", "-o", &dir, "tests/unit1.pi"], 0);
+    assert_eq!(fs::read_to_string(format!("{}/unit1.c", dir)).unwrap(), expected("unit1", "out"));
+}
+
+#[test]
+fn tokens () {
+    check("tokens", &["tokens", "-t", "tests/test.t", "tests/join.pi"]);
+}
+
+#[test]
+fn ast () {
+    check("ast", &["ast", "-t", "tests/test.t", "tests/unit1.pi"]);
+}
+
+#[test]
+fn parse () {
+    check("parse", &["check", "-t", "tests/parse.t"]);
+}
+
+#[test]
+fn undefined () {
+    check("undefined", &["check", "-t", "tests/undefined.t"]);
+}
+
+#[test]
+fn recursive () {
+    check("recursive", &["check", "-t", "tests/recursive.t"]);
+}
+
+#[test]
+fn alternation () {
+    build("alternation.t", "alternation.pi");
+}
+
+#[test]
+fn duplicate () {
+    check("duplicate", &["check", "-t", "tests/duplicate.t"]);
+}
+
+#[test]
+fn operators () {
+    build("operators.t", "operators.pi");
+}
+
+#[test]
+fn nonassoc () {
+    build("operators.t", "nonassoc.pi");
+}
+
+#[test]
+fn symbols () {
+    build("symbols.t", "symbols.pi");
+}
+
+#[test]
+fn spaced () {
+    build("symbols.t", "spaced.pi");
+}
+
+#[test]
+fn word () {
+    build("word.t", "word.pi");
+}
+
+#[test]
+fn badword () {
+    build("word.t", "badword.pi");
+}
+
+#[test]
+fn spaces () {
+    build("spaces.t", "spaces.pi");
+}
+
+#[test]
+fn tabs () {
+    build("tabs.t", "tabs.pi");
+}
+
+#[test]
+fn width () {
+    build("width.t", "width.pi");
+}
+
+#[test]
+fn mixed () {
+    build("test.t", "mixed.pi");
+}
+
+#[test]
+fn split () {
+    build("split.t", "split.pi");
+}
+
+#[test]
+fn footer () {
+    build("test.t", "footer.pi");
+}
@@ -0,0 +1,8 @@
+!/def/string "
+!/def/multiline-string """
+
+say:
+    say := say s:STRING EOL
+        [output]
+            warning: s, "strings are not translated"
+            write: puts({s});
@@ -0,0 +1,6 @@
+r:
+    v := x:WORD*
+        [output]
+            write: {x}
+    w := (x:WORD)?
+    e := ()
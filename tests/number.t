@@ -0,0 +1,8 @@
+!/def/number decimal exponent hex octal binary separator suffix
+!/def/extension c
+
+
+number:
+    number := n:NUMBER EOL
+        [output]
+            write: {n} ;
@@ -0,0 +1,41 @@
+!/def/symbols ==
+
+statement:
+    expr := e:expr EOL
+        [output]
+            write: {e};
+
+op!
+    equ := ==
+        [output]
+            write: ==
+    add := +
+        [output]
+            write: +
+    sub := -
+        [output]
+            write: -
+    mul := *
+        [output]
+            write: *
+    div := /
+        [output]
+            write: /
+    pow := ^
+        [output]
+            write: ^
+
+expr!
+    binary := l:expr o:op r:expr
+        [output]
+            write: \( {l} {op} {r} )
+
+    num := n:NUMBER
+        [output]
+            write: {n}
+
+    [operators]
+        nonassoc: ==
+        left: + -
+        left: * /
+        right: ^
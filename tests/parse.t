@@ -0,0 +1,12 @@
+empty:
+
+stray line
+
+bad:
+    nothing :=
+    symbol := s:, EOL
+    open := (x:WORD EOL
+    close := x:WORD) EOL
+    v := x:WORD EOL
+        [output]
+    5
@@ -0,0 +1,6 @@
+!/def/string "
+
+statement:
+    print := print \( s:STRING \) EOL
+        [output]
+            write: print {s}
@@ -0,0 +1,30 @@
+statement:
+    call := f:call EOL
+        [output]
+            write: {f};
+
+call!
+    direct := c:call . m:WORD
+        [output]
+            write: {c}.{m}
+    name := n:WORD
+        [output]
+            write: {n}
+
+a!
+    r := x:n y:a
+        [output]
+            write: {y}
+    w := w:WORD
+        [output]
+            write: {w}
+
+n!
+    m := y:NUMBER*
+        [output]
+            write: {y}
+
+hidden:
+    h := v:a EOL
+        [output]
+            write: {v};
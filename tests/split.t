@@ -0,0 +1,23 @@
+!/def/brackets ( )
+
+statement:
+    call := f:WORD \( e:expr \) EOL
+        [output]
+            write: {f}({e});
+
+op!
+    ne := \! =
+        [output]
+            write: !=
+
+expr!
+    binary := l:expr o:op r:expr
+        [output]
+            write: {l} {op} {r}
+
+    name := n:WORD
+        [output]
+            write: {n}
+
+    [operators]
+        left: !=
@@ -0,0 +1,9 @@
+!/def/symbols ==
+
+statement:
+    equal := a:WORD == b:WORD EOL
+        [output]
+            write: {a} == {b};
+    assign := a:WORD = b:WORD EOL
+        [output]
+            write: {a} = {b};
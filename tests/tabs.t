@@ -0,0 +1,12 @@
+!/def/indent tabs
+
+statement:
+	if := if w:WORD EOL INDENT
+		[output]
+			write: if \( {w} ) {
+	call := f:WORD EOL
+		[output]
+			write: {f}();
+	end := DEDENT
+		[output]
+			write: }
@@ -1,44 +1,31 @@
-!/def/comment /* */ //
+!/def/line-comment //
+!/def/block-comment /* */
 !/def/string " '
+!/def/extension c
+!/def/symbols == := -> <= >=
+!/def/word start=_
+!/def/brackets ( )
+!/def/continuation \
 
 
 variable:
-    leti := let name:WORD \: type:WORD = e:expr EOL
+    let := let name:WORD (\: type:WORD)? (= e:expr)? EOL
         [output]
-            write: const {type} {name} = {expr} ;
+            error unless e: name, "immutable variable must be initialized"
+            write if type: {@comments}const {type} {name} = {expr} ;
+            write unless type: {@comments}const __typeof__({expr}) {name} = {expr} ;
 
-    letiinf := let name:WORD = e:expr EOL
+    mut := mut name:WORD (\: type:WORD)? (= e:expr)? EOL
         [output]
-            write: const __typeof__({expr}) {name} = {expr} ;
-
-    let := let name:WORD \: type:WORD EOL
-        [output]
-            error: name, "immutable variable must be initialized"
-
-    letinf := let name:WORD EOL
-        [output]
-            error: name, "immutable variable must be initialized"
-
-    muti := mut name:WORD \: type:WORD = e:expr EOL
-        [output]
-            write: {type} {name} = {expr} ;
-
-    mutiinf := mut name:WORD = e:expr EOL
-        [output]
-            write: __typeof__({expr}) {name} = {expr} ;
-
-    mut := mut name:WORD \: type:WORD EOL
-        [output]
-            write: {type} {name} ;
-
-    mutinf := mut name:WORD EOL
-        [output]
-            error: name, "cross-code type inference not yet implemented"
+            error unless type unless e: name, "cross-code type inference not yet implemented"
+            write if type if e: {@comments}{type} {name} = {expr} ;{@trailing}
+            write if type unless e: {@comments}{type} {name} ;{@trailing}
+            write unless type: {@comments}__typeof__({expr}) {name} = {expr} ;{@trailing}
 
 assign:
     assign := name:WORD = e:expr EOL
         [output]
-            write: {name} = {expr} ;
+            write: {@comments}{name} = {expr} ;{@trailing}
 
 op!
     equ := ==
@@ -55,48 +42,52 @@ op!
             write: -
 
 expr!
-    binary := t:term o:op e:expr
+    binary := l:expr o:op r:expr
         [output]
-            write: {term} {op} {expr}
+            write: {l} {op} {r}
 
     term := t:term
         [output]
             write: {term}
 
+    [operators]
+        nonassoc: == >
+        left: + -
+
 if:
     if := if e:expr EOL INDENT
         [output]
             write: if \( {expr} ) {
 
 func:
-    fdef := fname:WORD \: type:WORD ( p:pars ) EOL INDENT
+    fdef := fname:WORD \: type:WORD \( p:pars \) EOL INDENT
         [output]
-            write: {type} {fname} \( {p} ) {
+            write: {@comments}{type} {fname} \( {p} ) {
 
-    fdefinf := fname:WORD \: ( p:pars ) EOL INDENT
+    fdefinf := fname:WORD \: \( p:pars \) EOL INDENT
         [output]
             error: fname, "return type inference not yet implemented"
 
-    fdefv := fname:WORD \: type:WORD () EOL INDENT
+    fdefv := fname:WORD \: type:WORD \( \) EOL INDENT
         [output]
-            write: {type} {fname} \( void ) {
+            write: {@comments}{type} {fname} \( void ) {
 
-    fdec := fname:WORD \: type:WORD ( p:pars ) EOL
+    fdec := fname:WORD \: type:WORD \( p:pars \) EOL
         [output]
-            write: {type} {fname} \( {p} ) ;
+            write: {@comments}{type} {fname} \( {p} ) ;
 
-    fdecv := fname:WORD \: type:WORD () EOL
+    fdecv := fname:WORD \: type:WORD \( \) EOL
         [output]
-            write: {type} {fname} \( void ) ;
+            write: {@comments}{type} {fname} \( void ) ;
 
     ret := return e:expr EOL
         [output]
             write: return {expr} ;
 
 call:
-    call := fname:WORD ( a:args ) EOL
+    call := fname:WORD \( a:expr % , \) EOL
         [output]
-            write: {fname} \( {a} )
+            write: {fname} \( {a, " , "} )
 
 while:
     while:= while e:expr EOL INDENT
@@ -112,7 +103,7 @@ pars!
         [output]
             error: name, "parameter type not specified"
 
-    tpar := name:WORD \: name:WORD
+    tpar := name:WORD \: type:WORD
         [output]
             write: {type} {name}
 
@@ -120,19 +111,10 @@ pars!
         [output]
             error: name, "parameter type not specified"
 
-args!
-    arg := arg:expr , a:args
-        [output]
-            write: {arg} , {a}
-
-    arg := arg:expr
-        [output]
-            write: {arg}
-
 term!
-    call := fname:WORD ( a:args )
+    call := fname:WORD \( a:expr % , \)
         [output]
-            write: {fname} \( {a} )
+            write: {fname} \( {a, " , "} )
 
     number := num:NUMBER
         [output]
@@ -0,0 +1,12 @@
+statement:
+    let := let n:WORD = e:exprr EOL
+        [output]
+            write: {n} = {e};
+    print := print v:FOO EOL
+        [output]
+            write: print({v});
+
+expr!
+    num := n:NUMBER
+        [output]
+            write: {n}
@@ -0,0 +1,6 @@
+!/def/word start=_ continue=?-
+
+statement:
+    call := f:WORD \( \) EOL
+        [output]
+            write: {f}();